@@ -0,0 +1,4 @@
+ALTER TABLE oauth2_clients ADD COLUMN is_trusted BOOL NOT NULL DEFAULT FALSE;
+
+-- Logging in to Miniboss itself should never ask for consent
+UPDATE oauth2_clients SET is_trusted = TRUE WHERE is_internal = TRUE;
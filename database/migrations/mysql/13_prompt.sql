@@ -0,0 +1,3 @@
+ALTER TABLE oauth2_pending_authorizations ADD COLUMN force_consent BOOL NOT NULL DEFAULT FALSE;
+
+ALTER TABLE sso_sessions ADD COLUMN multi_factor BOOL NOT NULL DEFAULT FALSE;
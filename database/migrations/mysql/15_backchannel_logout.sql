@@ -0,0 +1,13 @@
+ALTER TABLE oauth2_clients ADD COLUMN backchannel_logout_uri TEXT DEFAULT NULL;
+
+CREATE TABLE backchannel_logout_deliveries (
+    id VARCHAR(32) NOT NULL,
+    client_id VARCHAR(32) NOT NULL,
+    user_id VARCHAR(64) NOT NULL,
+    created_at BIGINT NOT NULL,
+    attempts INT NOT NULL,
+    last_attempt_at BIGINT DEFAULT NULL,
+    delivered_at BIGINT DEFAULT NULL,
+    last_error TEXT DEFAULT NULL,
+    PRIMARY KEY (id)
+);
@@ -0,0 +1,4 @@
+ALTER TABLE oauth2_pending_authorizations ADD COLUMN created_at BIGINT NOT NULL DEFAULT 0;
+
+-- Give existing authorizations a full TTL, rather than purging them right away
+UPDATE oauth2_pending_authorizations SET created_at = UNIX_TIMESTAMP();
@@ -0,0 +1,12 @@
+-- Carried from the authorization request and the login into the ID token
+ALTER TABLE oauth2_pending_authorizations ADD COLUMN nonce TEXT DEFAULT NULL;
+ALTER TABLE oauth2_pending_authorizations ADD COLUMN auth_time BIGINT DEFAULT NULL;
+ALTER TABLE oauth2_pending_authorizations ADD COLUMN multi_factor BOOL NOT NULL DEFAULT FALSE;
+
+ALTER TABLE oauth2_authorization_codes ADD COLUMN nonce TEXT DEFAULT NULL;
+ALTER TABLE oauth2_authorization_codes ADD COLUMN auth_time BIGINT DEFAULT NULL;
+ALTER TABLE oauth2_authorization_codes ADD COLUMN multi_factor BOOL NOT NULL DEFAULT FALSE;
+
+-- ID tokens issued on refresh report the original login
+ALTER TABLE oauth2_refresh_tokens ADD COLUMN auth_time BIGINT DEFAULT NULL;
+ALTER TABLE oauth2_refresh_tokens ADD COLUMN multi_factor BOOL NOT NULL DEFAULT FALSE;
@@ -0,0 +1,15 @@
+CREATE TABLE user_totp (
+    user_id VARCHAR(64) NOT NULL,
+    secret VARCHAR(64) NOT NULL,
+    confirmed BOOL NOT NULL,
+    last_used_step BIGINT NOT NULL,
+    PRIMARY KEY (user_id)
+);
+
+CREATE TABLE user_recovery_codes (
+    user_id VARCHAR(64) NOT NULL,
+    code VARCHAR(64) NOT NULL,
+    PRIMARY KEY (user_id, code)
+);
+
+ALTER TABLE oauth2_pending_authorizations ADD COLUMN pending_user_id VARCHAR(64) DEFAULT NULL;
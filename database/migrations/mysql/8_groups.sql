@@ -0,0 +1,18 @@
+CREATE TABLE user_groups (
+    group_id VARCHAR(32) NOT NULL,
+    name VARCHAR(64) NOT NULL,
+    PRIMARY KEY (group_id),
+    UNIQUE (name)
+);
+
+CREATE TABLE user_group_members (
+    group_id VARCHAR(32) NOT NULL,
+    user_id VARCHAR(64) NOT NULL,
+    PRIMARY KEY (group_id, user_id)
+);
+
+CREATE TABLE user_group_permitted_scopes (
+    group_id VARCHAR(32) NOT NULL,
+    scope VARCHAR(64) NOT NULL,
+    PRIMARY KEY (group_id, scope)
+);
@@ -0,0 +1,31 @@
+CREATE TABLE scopes (
+    name VARCHAR(64) NOT NULL,
+    description TEXT NOT NULL,
+    is_default BOOL NOT NULL,
+    PRIMARY KEY (name)
+);
+
+CREATE TABLE oauth2_client_scopes (
+    client_id VARCHAR(32) NOT NULL,
+    scope VARCHAR(64) NOT NULL,
+    PRIMARY KEY (client_id, scope)
+);
+
+INSERT INTO scopes (name, description, is_default) VALUES
+    ('openid', 'Sign you in', TRUE),
+    ('profile', 'View your name', TRUE),
+    ('email', 'View your email address', TRUE),
+    ('groups', 'View the groups you are a member of', TRUE);
+
+-- Register the scopes which were in use before the registry existed
+INSERT INTO scopes (name, description, is_default)
+    SELECT existing.scope, existing.scope, FALSE FROM (
+        SELECT scope FROM user_permitted_scopes
+        UNION
+        SELECT scope FROM user_group_permitted_scopes
+    ) AS existing
+    WHERE existing.scope NOT IN ('openid', 'profile', 'email', 'groups');
+
+-- Existing clients could request any scope, keep it that way
+INSERT INTO oauth2_client_scopes (client_id, scope)
+    SELECT oauth2_clients.client_id, scopes.name FROM oauth2_clients CROSS JOIN scopes;
@@ -0,0 +1,13 @@
+CREATE TABLE oauth2_consents (
+    user_id VARCHAR(64) NOT NULL,
+    client_id VARCHAR(32) NOT NULL,
+    granted_at BIGINT NOT NULL,
+    PRIMARY KEY (user_id, client_id)
+);
+
+CREATE TABLE oauth2_consent_scopes (
+    user_id VARCHAR(64) NOT NULL,
+    client_id VARCHAR(32) NOT NULL,
+    scope VARCHAR(64) NOT NULL,
+    PRIMARY KEY (user_id, client_id, scope)
+);
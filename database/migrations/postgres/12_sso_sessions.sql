@@ -0,0 +1,9 @@
+CREATE TABLE sso_sessions (
+    id VARCHAR(32) NOT NULL,
+    token_hash VARCHAR(64) NOT NULL,
+    user_id VARCHAR(64) NOT NULL,
+    authenticated_at BIGINT NOT NULL,
+    expires_at BIGINT NOT NULL,
+    PRIMARY KEY (id),
+    UNIQUE (token_hash)
+);
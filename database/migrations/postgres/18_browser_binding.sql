@@ -0,0 +1,2 @@
+-- Hash of the value of the cookie set in the browser which started the authorization
+ALTER TABLE oauth2_pending_authorizations ADD COLUMN browser_binding_hash VARCHAR(64) DEFAULT NULL;
@@ -0,0 +1,4 @@
+-- Redeemed codes are kept until they expire, so a replay can revoke the tokens they produced
+ALTER TABLE oauth2_authorization_codes ADD COLUMN consumed_at BIGINT DEFAULT NULL;
+ALTER TABLE oauth2_authorization_codes ADD COLUMN access_token VARCHAR(32) DEFAULT NULL;
+ALTER TABLE oauth2_authorization_codes ADD COLUMN refresh_token VARCHAR(32) DEFAULT NULL;
@@ -0,0 +1,17 @@
+CREATE TABLE user_webauthn_credentials (
+    credential_id VARCHAR(255) NOT NULL,
+    user_id VARCHAR(64) NOT NULL,
+    name VARCHAR(64) NOT NULL,
+    passkey TEXT NOT NULL,
+    created_at BIGINT NOT NULL,
+    last_used_at BIGINT DEFAULT NULL,
+    PRIMARY KEY (credential_id)
+);
+
+CREATE TABLE webauthn_challenges (
+    id VARCHAR(32) NOT NULL,
+    subject VARCHAR(64) NOT NULL,
+    state TEXT NOT NULL,
+    expires_at BIGINT NOT NULL,
+    PRIMARY KEY (id)
+);
@@ -0,0 +1,5 @@
+CREATE TABLE oauth2_client_post_logout_redirect_uris (
+    client_id VARCHAR(32) NOT NULL,
+    uri VARCHAR(512) NOT NULL,
+    PRIMARY KEY (client_id, uri)
+);
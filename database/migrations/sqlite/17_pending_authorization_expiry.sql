@@ -0,0 +1,3 @@
+ALTER TABLE oauth2_pending_authorizations ADD COLUMN expires_at BIGINT NOT NULL DEFAULT 0;
+
+-- Existing authorizations are given the configured lifetime once migrated, see `OAuth2PendingAuthorization::backfill_expiry`
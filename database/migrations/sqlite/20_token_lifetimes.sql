@@ -0,0 +1,7 @@
+-- Per-client overrides of the configured lifetimes, in seconds
+ALTER TABLE oauth2_clients ADD COLUMN access_token_lifetime BIGINT DEFAULT NULL;
+ALTER TABLE oauth2_clients ADD COLUMN authorization_code_lifetime BIGINT DEFAULT NULL;
+ALTER TABLE oauth2_clients ADD COLUMN refresh_token_lifetime BIGINT DEFAULT NULL;
+
+-- Existing refresh tokens never expire
+ALTER TABLE oauth2_refresh_tokens ADD COLUMN expires_at BIGINT DEFAULT NULL;
@@ -0,0 +1,2 @@
+-- The refresh token an access token was issued with, so it can be revoked along with the refresh token
+ALTER TABLE oauth2_access_tokens ADD COLUMN refresh_token VARCHAR(32) DEFAULT NULL;
@@ -0,0 +1,8 @@
+CREATE TABLE login_throttles (
+    kind VARCHAR(16) NOT NULL,
+    subject VARCHAR(255) NOT NULL,
+    failed_attempts INT NOT NULL,
+    last_failed_at BIGINT NOT NULL,
+    locked_until BIGINT DEFAULT NULL,
+    PRIMARY KEY (kind, subject)
+);
@@ -0,0 +1 @@
+ALTER TABLE user_credentials ADD COLUMN pepper_version INT NOT NULL DEFAULT 0;
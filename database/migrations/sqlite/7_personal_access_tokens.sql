@@ -0,0 +1,16 @@
+-- The original table was never used, and has no owner to attach existing rows to
+DROP TABLE constant_access_tokens;
+
+CREATE TABLE constant_access_tokens (
+    id VARCHAR(32) NOT NULL,
+    name VARCHAR(64) NOT NULL,
+    token_hash VARCHAR(64) NOT NULL,
+    user_id VARCHAR(64) NOT NULL,
+    scopes TEXT DEFAULT NULL,
+    created_at BIGINT NOT NULL,
+    expires_at BIGINT DEFAULT NULL,
+    last_used_at BIGINT DEFAULT NULL,
+    PRIMARY KEY (id),
+    UNIQUE (token_hash),
+    UNIQUE (user_id, name)
+);
@@ -0,0 +1,87 @@
+use serde::Serialize;
+use sqlx::{FromRow, Result};
+use time::OffsetDateTime;
+
+use crate::driver::Database;
+use crate::generate_string;
+use crate::with_pool;
+
+/// A back-channel logout notification sent to a client.
+/// Records whether it was delivered, so failures can be inspected.
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct LogoutDelivery {
+    /// Also used as the `jti` of the logout token
+    pub id: String,
+    pub client_id: String,
+    pub user_id: String,
+    pub created_at: i64,
+    pub attempts: i32,
+    pub last_attempt_at: Option<i64>,
+    pub delivered_at: Option<i64>,
+    /// The error of the last failed attempt
+    pub last_error: Option<String>,
+}
+
+impl LogoutDelivery {
+    pub async fn new(driver: &Database, client_id: &str, user_id: &str) -> Result<Self> {
+        let delivery = Self {
+            id: generate_string(32),
+            client_id: client_id.to_string(),
+            user_id: user_id.to_string(),
+            created_at: OffsetDateTime::now_utc().unix_timestamp(),
+            attempts: 0,
+            last_attempt_at: None,
+            delivered_at: None,
+            last_error: None,
+        };
+
+        with_pool!(driver, |pool, sql| {
+            sqlx::query(&sql("INSERT INTO backchannel_logout_deliveries (id, client_id, user_id, created_at, attempts) VALUES (?, ?, ?, ?, 0)"))
+                .bind(&delivery.id)
+                .bind(&delivery.client_id)
+                .bind(&delivery.user_id)
+                .bind(delivery.created_at)
+                .execute(pool)
+                .await
+                .map(|result| result.rows_affected())
+        })?;
+
+        Ok(delivery)
+    }
+
+    /// All deliveries to the client, newest first
+    pub async fn list_by_client_id(driver: &Database, client_id: &str) -> Result<Vec<Self>> {
+        with_pool!(driver, |pool, sql| {
+            sqlx::query_as(&sql("SELECT * FROM backchannel_logout_deliveries WHERE client_id = ? ORDER BY created_at DESC"))
+                .bind(client_id)
+                .fetch_all(pool)
+                .await
+        })
+    }
+
+    /// Record the outcome of a delivery attempt. `error` is `None` if the attempt succeeded.
+    pub async fn record_attempt(&mut self, driver: &Database, error: Option<String>) -> Result<()> {
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+        self.attempts += 1;
+        self.last_attempt_at = Some(now);
+
+        match error {
+            Some(error) => self.last_error = Some(error),
+            None => self.delivered_at = Some(now),
+        }
+
+        with_pool!(driver, |pool, sql| {
+            sqlx::query(&sql("UPDATE backchannel_logout_deliveries SET attempts = ?, last_attempt_at = ?, delivered_at = ?, last_error = ? WHERE id = ?"))
+                .bind(self.attempts)
+                .bind(self.last_attempt_at)
+                .bind(self.delivered_at)
+                .bind(&self.last_error)
+                .bind(&self.id)
+                .execute(pool)
+                .await
+                .map(|result| result.rows_affected())
+        })?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,132 @@
+use std::collections::HashSet;
+
+use sqlx::{FromRow, Result};
+use time::OffsetDateTime;
+
+use crate::driver::Database;
+use crate::with_pool;
+
+/// A user's consent for a client to access their account
+#[derive(Debug, Clone, FromRow)]
+pub struct Consent {
+    pub user_id: String,
+    pub client_id: String,
+    /// When consent was last given
+    pub granted_at: i64,
+}
+
+impl Consent {
+    /// Record the user's consent for the client to use the scopes.
+    /// Scopes consented to earlier are kept.
+    pub async fn grant(driver: &Database, user_id: &str, client_id: &str, scopes: &HashSet<String>) -> Result<()> {
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+        with_pool!(driver, |pool, sql| {
+            let mut tx = pool.begin().await?;
+
+            let existing: Option<Self> = sqlx::query_as(&sql("SELECT * FROM oauth2_consents WHERE user_id = ? AND client_id = ?"))
+                .bind(user_id)
+                .bind(client_id)
+                .fetch_optional(&mut *tx)
+                .await?;
+
+            if existing.is_some() {
+                sqlx::query(&sql("UPDATE oauth2_consents SET granted_at = ? WHERE user_id = ? AND client_id = ?"))
+                    .bind(now)
+                    .bind(user_id)
+                    .bind(client_id)
+                    .execute(&mut *tx)
+                    .await?;
+            } else {
+                sqlx::query(&sql("INSERT INTO oauth2_consents (user_id, client_id, granted_at) VALUES (?, ?, ?)"))
+                    .bind(user_id)
+                    .bind(client_id)
+                    .bind(now)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+
+            let consented_scopes: Vec<String> = sqlx::query_scalar(&sql("SELECT scope FROM oauth2_consent_scopes WHERE user_id = ? AND client_id = ?"))
+                .bind(user_id)
+                .bind(client_id)
+                .fetch_all(&mut *tx)
+                .await?;
+
+            for scope in scopes.iter().filter(|scope| !consented_scopes.contains(scope)) {
+                sqlx::query(&sql("INSERT INTO oauth2_consent_scopes (user_id, client_id, scope) VALUES (?, ?, ?)"))
+                    .bind(user_id)
+                    .bind(client_id)
+                    .bind(scope)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+
+            tx.commit().await?;
+        });
+        Ok(())
+    }
+
+    pub async fn get(driver: &Database, user_id: &str, client_id: &str) -> Result<Option<Self>> {
+        with_pool!(driver, |pool, sql| {
+            sqlx::query_as(&sql("SELECT * FROM oauth2_consents WHERE user_id = ? AND client_id = ?"))
+                .bind(user_id)
+                .bind(client_id)
+                .fetch_optional(pool)
+                .await
+        })
+    }
+
+    pub async fn list_by_user_id(driver: &Database, user_id: &str) -> Result<Vec<Self>> {
+        with_pool!(driver, |pool, sql| {
+            sqlx::query_as(&sql("SELECT * FROM oauth2_consents WHERE user_id = ?"))
+                .bind(user_id)
+                .fetch_all(pool)
+                .await
+        })
+    }
+
+    /// The scopes the user has consented to
+    pub async fn scopes(&self, driver: &Database) -> Result<HashSet<String>> {
+        let scopes: Vec<String> = with_pool!(driver, |pool, sql| {
+            sqlx::query_scalar(&sql("SELECT scope FROM oauth2_consent_scopes WHERE user_id = ? AND client_id = ?"))
+                .bind(&self.user_id)
+                .bind(&self.client_id)
+                .fetch_all(pool)
+                .await
+        })?;
+
+        Ok(scopes.into_iter().collect())
+    }
+
+    /// Whether the user has already consented to the client using all of the scopes
+    pub async fn covers(driver: &Database, user_id: &str, client_id: &str, scopes: &HashSet<String>) -> Result<bool> {
+        match Self::get(driver, user_id, client_id).await? {
+            Some(consent) => Ok(scopes.is_subset(&consent.scopes(driver).await?)),
+            None => Ok(false),
+        }
+    }
+
+    /// Revoke the consent. All tokens and authorization codes
+    /// the client holds for the user are deleted as well.
+    pub async fn revoke(self, driver: &Database) -> Result<()> {
+        with_pool!(driver, |pool, sql| {
+            let mut tx = pool.begin().await?;
+
+            for query in [
+                "DELETE FROM oauth2_consent_scopes WHERE user_id = ? AND client_id = ?",
+                "DELETE FROM oauth2_consents WHERE user_id = ? AND client_id = ?",
+                "DELETE FROM oauth2_access_tokens WHERE user_id = ? AND client_id = ?",
+                "DELETE FROM oauth2_refresh_tokens WHERE user_id = ? AND client_id = ?",
+                "DELETE FROM oauth2_authorization_codes WHERE user_id = ? AND client_id = ?",
+            ] {
+                sqlx::query(&sql(query))
+                    .bind(&self.user_id)
+                    .bind(&self.client_id)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+
+            tx.commit().await?;
+        });
+        Ok(())
+    }
+}
@@ -0,0 +1,186 @@
+use serde::Serialize;
+use sqlx::{FromRow, Result};
+
+use crate::driver::Database;
+use crate::generate_string;
+use crate::with_pool;
+
+/// A group of users. Members are permitted all scopes of the group.
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct Group {
+    pub group_id: String,
+    pub name: String,
+}
+
+impl Group {
+    pub async fn new(driver: &Database, name: String) -> Result<Self> {
+        let group_id = generate_string(32);
+
+        with_pool!(driver, |pool, sql| {
+            sqlx::query(&sql("INSERT INTO user_groups (group_id, name) VALUES (?, ?)"))
+                .bind(&group_id)
+                .bind(&name)
+                .execute(pool)
+                .await
+                .map(|result| result.rows_affected())
+        })?;
+
+        Ok(Self {
+            group_id,
+            name,
+        })
+    }
+
+    pub async fn list(driver: &Database) -> Result<Vec<Self>> {
+        with_pool!(driver, |pool, sql| {
+            sqlx::query_as(&sql("SELECT * FROM user_groups"))
+                .fetch_all(pool)
+                .await
+        })
+    }
+
+    pub async fn get_by_id(driver: &Database, group_id: &str) -> Result<Option<Self>> {
+        with_pool!(driver, |pool, sql| {
+            sqlx::query_as(&sql("SELECT * FROM user_groups WHERE group_id = ?"))
+                .bind(group_id)
+                .fetch_optional(pool)
+                .await
+        })
+    }
+
+    pub async fn get_by_name(driver: &Database, name: &str) -> Result<Option<Self>> {
+        with_pool!(driver, |pool, sql| {
+            sqlx::query_as(&sql("SELECT * FROM user_groups WHERE name = ?"))
+                .bind(name)
+                .fetch_optional(pool)
+                .await
+        })
+    }
+
+    /// All groups the user is a member of
+    pub async fn list_by_user_id(driver: &Database, user_id: &str) -> Result<Vec<Self>> {
+        with_pool!(driver, |pool, sql| {
+            sqlx::query_as(&sql("SELECT g.* FROM user_groups g JOIN user_group_members m ON g.group_id = m.group_id WHERE m.user_id = ?"))
+                .bind(user_id)
+                .fetch_all(pool)
+                .await
+        })
+    }
+
+    /// Delete the group, its memberships and its scopes
+    pub async fn delete(self, driver: &Database) -> Result<()> {
+        with_pool!(driver, |pool, sql| {
+            let mut tx = pool.begin().await?;
+
+            sqlx::query(&sql("DELETE FROM user_group_members WHERE group_id = ?"))
+                .bind(&self.group_id)
+                .execute(&mut *tx)
+                .await?;
+
+            sqlx::query(&sql("DELETE FROM user_group_permitted_scopes WHERE group_id = ?"))
+                .bind(&self.group_id)
+                .execute(&mut *tx)
+                .await?;
+
+            sqlx::query(&sql("DELETE FROM user_groups WHERE group_id = ?"))
+                .bind(&self.group_id)
+                .execute(&mut *tx)
+                .await?;
+
+            tx.commit().await?;
+        });
+        Ok(())
+    }
+
+    pub async fn list_members(&self, driver: &Database) -> Result<Vec<String>> {
+        with_pool!(driver, |pool, sql| {
+            sqlx::query_scalar(&sql("SELECT user_id FROM user_group_members WHERE group_id = ?"))
+                .bind(&self.group_id)
+                .fetch_all(pool)
+                .await
+        })
+    }
+
+    pub async fn add_member(&self, driver: &Database, user_id: &str) -> Result<()> {
+        with_pool!(driver, |pool, sql| {
+            sqlx::query(&sql("INSERT INTO user_group_members (group_id, user_id) VALUES (?, ?)"))
+                .bind(&self.group_id)
+                .bind(user_id)
+                .execute(pool)
+                .await
+                .map(|result| result.rows_affected())
+        })?;
+
+        Ok(())
+    }
+
+    pub async fn remove_member(&self, driver: &Database, user_id: &str) -> Result<()> {
+        with_pool!(driver, |pool, sql| {
+            sqlx::query(&sql("DELETE FROM user_group_members WHERE group_id = ? AND user_id = ?"))
+                .bind(&self.group_id)
+                .bind(user_id)
+                .execute(pool)
+                .await
+                .map(|result| result.rows_affected())
+        })?;
+
+        Ok(())
+    }
+
+    pub async fn is_member(&self, driver: &Database, user_id: &str) -> Result<bool> {
+        let member: Option<String> = with_pool!(driver, |pool, sql| {
+            sqlx::query_scalar(&sql("SELECT user_id FROM user_group_members WHERE group_id = ? AND user_id = ?"))
+                .bind(&self.group_id)
+                .bind(user_id)
+                .fetch_optional(pool)
+                .await
+        })?;
+
+        Ok(member.is_some())
+    }
+
+    pub async fn list_permitted_scopes(&self, driver: &Database) -> Result<Vec<String>> {
+        with_pool!(driver, |pool, sql| {
+            sqlx::query_scalar(&sql("SELECT scope FROM user_group_permitted_scopes WHERE group_id = ?"))
+                .bind(&self.group_id)
+                .fetch_all(pool)
+                .await
+        })
+    }
+
+    pub async fn grant_permitted_scope(&self, driver: &Database, scope: &str) -> Result<()> {
+        with_pool!(driver, |pool, sql| {
+            sqlx::query(&sql("INSERT INTO user_group_permitted_scopes (group_id, scope) VALUES (?, ?)"))
+                .bind(&self.group_id)
+                .bind(scope)
+                .execute(pool)
+                .await
+                .map(|result| result.rows_affected())
+        })?;
+
+        Ok(())
+    }
+
+    pub async fn remove_permitted_scope(&self, driver: &Database, scope: &str) -> Result<()> {
+        with_pool!(driver, |pool, sql| {
+            sqlx::query(&sql("DELETE FROM user_group_permitted_scopes WHERE group_id = ? AND scope = ?"))
+                .bind(&self.group_id)
+                .bind(scope)
+                .execute(pool)
+                .await
+                .map(|result| result.rows_affected())
+        })?;
+
+        Ok(())
+    }
+
+    /// All scopes the user is permitted through their groups
+    pub async fn list_permitted_scopes_for_user(driver: &Database, user_id: &str) -> Result<Vec<String>> {
+        with_pool!(driver, |pool, sql| {
+            sqlx::query_scalar(&sql("SELECT DISTINCT s.scope FROM user_group_permitted_scopes s JOIN user_group_members m ON s.group_id = m.group_id WHERE m.user_id = ?"))
+                .bind(user_id)
+                .fetch_all(pool)
+                .await
+        })
+    }
+}
@@ -1,45 +1,147 @@
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::{Algorithm, Argon2, Params, Version};
 use base64::Engine;
+use rand::RngCore;
 use sha2::Digest;
+use thiserror::Error;
 
-const BCRYPT_COST: u32 = 10;
+/// Password hashing settings. Changing these does not invalidate existing hashes,
+/// they are upgraded on the next successful login instead.
+#[derive(Debug, Clone)]
+pub struct PasswordHashing {
+    /// All peppers which may still be in use. New hashes use the one with the highest version.
+    pub peppers: Vec<Pepper>,
+    pub params: Argon2Params,
+}
+
+#[derive(Debug, Clone)]
+pub struct Pepper {
+    pub version: i32,
+    pub pepper: String,
+}
+
+/// Argon2id cost parameters
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Argon2Params {
+    /// Memory size in KiB
+    pub memory_kib: u32,
+    /// Number of iterations
+    pub iterations: u32,
+    /// Degree of parallelism
+    pub parallelism: u32,
+}
+
+#[derive(Debug, Error)]
+pub enum HashError {
+    #[error("{0}")]
+    Argon2(#[from] argon2::password_hash::Error),
+    #[error("{0}")]
+    BCrypt(#[from] bcrypt::BcryptError),
+    #[error("Unknown password hash format")]
+    UnknownFormat,
+    #[error("No pepper configured")]
+    NoPepper,
+    #[error("{0}")]
+    Task(#[from] tokio::task::JoinError),
+}
+
+impl Default for Argon2Params {
+    /// The parameters recommended by OWASP
+    fn default() -> Self {
+        Self {
+            memory_kib: 19 * 1024,
+            iterations: 2,
+            parallelism: 1,
+        }
+    }
+}
+
+impl PasswordHashing {
+    /// The pepper new hashes should be created with
+    pub fn current_pepper(&self) -> Option<&Pepper> {
+        self.peppers.iter().max_by_key(|pepper| pepper.version)
+    }
+
+    pub fn pepper(&self, version: i32) -> Option<&Pepper> {
+        self.peppers.iter().find(|pepper| pepper.version == version)
+    }
+}
+
+impl Argon2Params {
+    fn to_params(&self) -> Result<Params, argon2::Error> {
+        Params::new(self.memory_kib, self.iterations, self.parallelism, None)
+    }
+}
 
 /// Generate a hash for the provided input.
-/// The returned String is a hash of the input and it's salt
-///
-/// # Panics
-///
-/// When the provided `salt` is not exactly 16 characters long
+/// The returned String is in the PHC string format, which includes
+/// the algorithm, its parameters and the salt.
 ///
 /// # Errors
 ///
-/// If hashing fails
-///
-pub fn hash(input: &str, salt: &str, pepper: &str) -> Result<String, bcrypt::BcryptError> {
-    let mut hasher = sha2::Sha512_256::new();
-
-    hasher.update(input);
-    hasher.update(pepper);
+/// If hashing fails, e.g. due to invalid parameters
+pub fn hash(input: &str, pepper: &str, params: &Argon2Params) -> Result<String, HashError> {
+    let params = params.to_params().map_err(argon2::password_hash::Error::from)?;
+    let argon2 = Argon2::new_with_secret(pepper.as_bytes(), Algorithm::Argon2id, Version::V0x13, params)
+        .map_err(argon2::password_hash::Error::from)?;
 
-    let salt_bytes = salt.as_bytes();
-    if salt_bytes.len() != 16 {
-        panic!("Salt is not 16 bytes long")
-    }
-    let mut salt_bytes_arr = [0_u8; 16];
-    salt_bytes_arr.copy_from_slice(salt_bytes);
+    let mut salt = [0_u8; 16];
+    rand::thread_rng().fill_bytes(&mut salt);
+    let salt = SaltString::encode_b64(&salt)?;
 
-    let engine = base64::prelude::BASE64_STANDARD;
-    let hash = engine.encode(hasher.finalize());
-    let bcrypt = bcrypt::hash_with_salt(hash, BCRYPT_COST, salt_bytes_arr)?.format_for_version(bcrypt::Version::TwoB);
-
-    Ok(bcrypt)
+    Ok(argon2.hash_password(input.as_bytes(), &salt)?.to_string())
 }
 
-/// Verify an input is the same as the stored hash. The same `pepper` must be used
+/// Verify an input is the same as the stored hash. The same `pepper` must be used.
+/// Both Argon2 and legacy bcrypt hashes are supported.
 ///
 /// # Errors
 ///
 /// If verifying fails
-pub fn verify(stored_hash: &str, input: &str, pepper: &str) -> Result<bool, bcrypt::BcryptError> {
+pub fn verify(stored_hash: &str, input: &str, pepper: &str) -> Result<bool, HashError> {
+    if stored_hash.starts_with("$argon2") {
+        let parsed = PasswordHash::new(stored_hash)?;
+        // The algorithm and parameters are taken from the stored hash
+        let argon2 = Argon2::new_with_secret(pepper.as_bytes(), Algorithm::default(), Version::default(), Params::default())
+            .map_err(argon2::password_hash::Error::from)?;
+
+        match argon2.verify_password(input.as_bytes(), &parsed) {
+            Ok(()) => Ok(true),
+            Err(argon2::password_hash::Error::Password) => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    } else if stored_hash.starts_with("$2") {
+        verify_bcrypt(stored_hash, input, pepper)
+    } else {
+        Err(HashError::UnknownFormat)
+    }
+}
+
+/// Whether the stored hash was created with a different algorithm or parameters than currently configured
+pub fn needs_rehash(stored_hash: &str, params: &Argon2Params) -> bool {
+    let parsed = match PasswordHash::new(stored_hash) {
+        Ok(v) => v,
+        // Legacy bcrypt hashes are not in the PHC format
+        Err(_) => return true,
+    };
+
+    if parsed.algorithm != Algorithm::Argon2id.ident() {
+        return true;
+    }
+
+    match Params::try_from(&parsed) {
+        Ok(stored) => {
+            stored.m_cost() != params.memory_kib
+                || stored.t_cost() != params.iterations
+                || stored.p_cost() != params.parallelism
+        }
+        Err(_) => true,
+    }
+}
+
+/// Hashes created before Argon2 was introduced.
+/// These are peppered SHA-512/256 hashes, hashed again using bcrypt.
+fn verify_bcrypt(stored_hash: &str, input: &str, pepper: &str) -> Result<bool, HashError> {
     let mut hasher = sha2::Sha512_256::new();
 
     hasher.update(input);
@@ -51,4 +153,4 @@ pub fn verify(stored_hash: &str, input: &str, pepper: &str) -> Result<bool, bcry
     let correct = bcrypt::verify(hash, stored_hash)?;
 
     Ok(correct)
-}
\ No newline at end of file
+}
@@ -1,9 +1,23 @@
 pub mod driver;
 pub mod user;
 pub mod oauth2_client;
-mod hash;
+pub mod totp;
+pub mod webauthn;
+pub mod login_throttle;
+pub mod password_policy;
+pub mod personal_access_token;
+pub mod group;
+pub mod scope;
+pub mod consent;
+pub mod session;
+pub mod backchannel_logout;
+pub mod repository;
+pub mod purge;
+pub mod hash;
 
+use base64::Engine;
 use rand::Rng;
+use sha2::Digest;
 
 fn generate_string(len: usize) -> String {
     rand::thread_rng()
@@ -13,23 +27,34 @@ fn generate_string(len: usize) -> String {
         .collect()
 }
 
-/// Macro to show the type of an enum as a String.
+/// Hash a randomly generated secret for storage.
+/// Only suitable for high-entropy input, use [hash] for passwords.
+fn hash_secret(input: &str) -> String {
+    let engine = base64::prelude::BASE64_STANDARD;
+    engine.encode(sha2::Sha256::digest(input))
+}
+
+/// Macro to show the type of an enum as a String, on every supported backend.
 /// The enum itself should only implement [sqlx::Encode] and [sqlx::Decode]
 // Issue: https://github.com/launchbadge/sqlx/issues/1241
 // Comment: https://github.com/launchbadge/sqlx/issues/1241#issuecomment-1649040626
 #[macro_export]
 macro_rules! impl_enum_type {
     ($ty:ty) => {
-        impl sqlx::Type<sqlx::MySql> for $ty {
-            fn type_info() -> <sqlx::MySql as sqlx::Database>::TypeInfo {
-                <str as sqlx::Type<sqlx::MySql>>::type_info()
+        $crate::impl_enum_type!($ty, sqlx::MySql);
+        $crate::impl_enum_type!($ty, sqlx::Postgres);
+        #[cfg(feature = "sqlite")]
+        $crate::impl_enum_type!($ty, sqlx::Sqlite);
+    };
+    ($ty:ty, $db:ty) => {
+        impl sqlx::Type<$db> for $ty {
+            fn type_info() -> <$db as sqlx::Database>::TypeInfo {
+                <str as sqlx::Type<$db>>::type_info()
             }
 
-            fn compatible(ty: &<sqlx::MySql as sqlx::Database>::TypeInfo) -> bool {
-                <str as sqlx::Type<sqlx::MySql>>::compatible(ty)
+            fn compatible(ty: &<$db as sqlx::Database>::TypeInfo) -> bool {
+                <str as sqlx::Type<$db>>::compatible(ty)
             }
         }
     };
 }
-
-
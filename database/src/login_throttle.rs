@@ -0,0 +1,204 @@
+use sqlx::{Decode, Encode, Error, FromRow, Result};
+use time::OffsetDateTime;
+
+use crate::driver::Database;
+use crate::impl_enum_type;
+use crate::with_pool;
+
+/// How often [LoginThrottle::try_attempt] retries after a concurrent attempt changed the throttle
+const MAX_ATTEMPT_RETRIES: usize = 5;
+
+/// What failed login attempts are counted against
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Encode, Decode)]
+pub enum ThrottleKind {
+    /// The account being logged in to, identified by the username as entered.
+    /// This way accounts that do not exist are throttled the same as ones that do.
+    Account,
+    /// The source IP address of the attempt
+    Ip,
+}
+
+impl_enum_type!(ThrottleKind);
+
+#[derive(Debug, Clone)]
+pub struct ThrottlePolicy {
+    /// Failed attempts allowed before delays are enforced
+    pub free_attempts: i32,
+    /// Upper bound of the delay between attempts, in seconds
+    pub max_delay: i64,
+    /// Failed attempts after which an account is locked
+    pub lockout_threshold: i32,
+    /// How long an account stays locked, in seconds
+    pub lockout_duration: i64,
+    /// Failed attempts older than this many seconds are forgotten
+    pub reset_after: i64,
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct LoginThrottle {
+    pub kind: ThrottleKind,
+    pub subject: String,
+    pub failed_attempts: i32,
+    pub last_failed_at: i64,
+    pub locked_until: Option<i64>,
+}
+
+impl LoginThrottle {
+    pub async fn get(driver: &Database, kind: ThrottleKind, subject: &str) -> Result<Option<Self>> {
+        with_pool!(driver, |pool, sql| {
+            sqlx::query_as(&sql("SELECT * FROM login_throttles WHERE kind = ? AND subject = ?"))
+                .bind(kind)
+                .bind(subject)
+                .fetch_optional(pool)
+                .await
+        })
+    }
+
+    /// The unix timestamp until which no further attempts are allowed,
+    /// or `None` if an attempt may be made right now.
+    pub fn blocked_until(&self, policy: &ThrottlePolicy) -> Option<i64> {
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+
+        if let Some(locked_until) = self.locked_until {
+            if locked_until > now {
+                return Some(locked_until);
+            }
+        }
+
+        if self.is_stale(policy, now) || self.failed_attempts <= policy.free_attempts {
+            return None;
+        }
+
+        // Double the delay for every failed attempt over the free ones
+        let exponent = (self.failed_attempts - policy.free_attempts - 1).min(32) as u32;
+        let delay = 2_i64.saturating_pow(exponent).min(policy.max_delay);
+
+        let allowed_at = self.last_failed_at + delay;
+        (allowed_at > now).then_some(allowed_at)
+    }
+
+    fn is_stale(&self, policy: &ThrottlePolicy, now: i64) -> bool {
+        self.last_failed_at + policy.reset_after < now
+    }
+
+    /// Count a login attempt against `subject`, if an attempt is allowed right now.
+    /// Attempts are counted before the credentials are checked, so parallel attempts can't slip past the limits.
+    /// Accounts are locked once they reach the lockout threshold, IP addresses are only delayed.
+    ///
+    /// Returns `false` if the attempt is not allowed.
+    pub async fn try_attempt(driver: &Database, kind: ThrottleKind, subject: &str, policy: &ThrottlePolicy) -> Result<bool> {
+        for _ in 0..MAX_ATTEMPT_RETRIES {
+            let now = OffsetDateTime::now_utc().unix_timestamp();
+            let current = Self::get(driver, kind, subject).await?;
+
+            if current.as_ref().and_then(|throttle| throttle.blocked_until(policy)).is_some() {
+                return Ok(false);
+            }
+
+            let mut next = current
+                .clone()
+                .filter(|v| !v.is_stale(policy, now))
+                .unwrap_or_else(|| Self::empty(kind, subject));
+
+            next.failed_attempts += 1;
+            next.last_failed_at = now;
+
+            if kind == ThrottleKind::Account && next.failed_attempts >= policy.lockout_threshold {
+                // Start counting from zero once the lock expires
+                next.locked_until = Some(now + policy.lockout_duration);
+                next.failed_attempts = 0;
+            }
+
+            let stored = match &current {
+                Some(current) => next.replace(driver, current).await?,
+                None => next.insert(driver).await?,
+            };
+
+            if stored {
+                return Ok(true);
+            }
+        }
+
+        // Lost the race for this subject every time, it is under heavy load
+        Ok(false)
+    }
+
+    /// Take back an attempt counted by [Self::try_attempt], because it succeeded
+    pub async fn forgive_attempt(driver: &Database, kind: ThrottleKind, subject: &str) -> Result<()> {
+        with_pool!(driver, |pool, sql| {
+            sqlx::query(&sql("UPDATE login_throttles SET failed_attempts = failed_attempts - 1 WHERE kind = ? AND subject = ? AND failed_attempts > 0"))
+                .bind(kind)
+                .bind(subject)
+                .execute(pool)
+                .await
+                .map(|result| result.rows_affected())
+        })?;
+
+        Ok(())
+    }
+
+    /// Store `self` in place of `current`, only if no one else changed it in the meantime.
+    /// Returns whether `self` was stored.
+    async fn replace(&self, driver: &Database, current: &Self) -> Result<bool> {
+        let updated = with_pool!(driver, |pool, sql| {
+            sqlx::query(&sql("UPDATE login_throttles SET failed_attempts = ?, last_failed_at = ?, locked_until = ? WHERE kind = ? AND subject = ? AND failed_attempts = ? AND last_failed_at = ?"))
+                .bind(self.failed_attempts)
+                .bind(self.last_failed_at)
+                .bind(self.locked_until)
+                .bind(self.kind)
+                .bind(&self.subject)
+                .bind(current.failed_attempts)
+                .bind(current.last_failed_at)
+                .execute(pool)
+                .await
+                .map(|result| result.rows_affected())
+        })?;
+
+        Ok(updated > 0)
+    }
+
+    /// Insert `self`. Returns `false` if a row for the subject was inserted in the meantime.
+    async fn insert(&self, driver: &Database) -> Result<bool> {
+        let inserted = with_pool!(driver, |pool, sql| {
+            sqlx::query(&sql("INSERT INTO login_throttles (failed_attempts, last_failed_at, locked_until, kind, subject) VALUES (?, ?, ?, ?, ?)"))
+                .bind(self.failed_attempts)
+                .bind(self.last_failed_at)
+                .bind(self.locked_until)
+                .bind(self.kind)
+                .bind(&self.subject)
+                .execute(pool)
+                .await
+                .map(|result| result.rows_affected())
+        });
+
+        match inserted {
+            Ok(_) => Ok(true),
+            Err(Error::Database(e)) if e.is_unique_violation() => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Forget all failed attempts, and lift the lock if there is one
+    pub async fn clear(driver: &Database, kind: ThrottleKind, subject: &str) -> Result<()> {
+        with_pool!(driver, |pool, sql| {
+            sqlx::query(&sql("DELETE FROM login_throttles WHERE kind = ? AND subject = ?"))
+                .bind(kind)
+                .bind(subject)
+                .execute(pool)
+                .await
+                .map(|result| result.rows_affected())
+        })?;
+
+        Ok(())
+    }
+
+    fn empty(kind: ThrottleKind, subject: &str) -> Self {
+        Self {
+            kind,
+            subject: subject.to_string(),
+            failed_attempts: 0,
+            last_failed_at: 0,
+            locked_until: None,
+        }
+    }
+}
@@ -1,9 +1,10 @@
 use crate::driver::Database;
-use crate::{generate_string, impl_enum_type};
+use crate::{generate_string, hash_secret, impl_enum_type};
 use sqlx::{Decode, Encode, FromRow, Result};
 use std::collections::HashSet;
 use thiserror::Error;
-use time::{Duration, OffsetDateTime};
+use time::OffsetDateTime;
+use crate::with_pool;
 
 #[derive(Debug, Clone, FromRow)]
 pub struct OAuth2Client {
@@ -12,6 +13,40 @@ pub struct OAuth2Client {
     pub client_id: String,
     pub client_secret: String,
     pub is_internal: bool,
+    /// Trusted clients are authorized without asking the user for consent
+    pub is_trusted: bool,
+    /// Notified when a user logs out of Miniboss
+    pub backchannel_logout_uri: Option<String>,
+    /// Overrides [TokenLifetimes::access_token]
+    pub access_token_lifetime: Option<i64>,
+    /// Overrides [TokenLifetimes::authorization_code]
+    pub authorization_code_lifetime: Option<i64>,
+    /// Overrides [TokenLifetimes::refresh_token]
+    pub refresh_token_lifetime: Option<i64>,
+}
+
+/// How long issued tokens and codes are valid, in seconds.
+/// Clients may override each of them.
+#[derive(Debug, Clone)]
+pub struct TokenLifetimes {
+    pub access_token: i64,
+    pub authorization_code: i64,
+    /// `None` if refresh tokens never expire
+    pub refresh_token: Option<i64>,
+    /// Not overridden per client
+    pub pending_authorization: i64,
+}
+
+/// The parameters of the authorization request a pending authorization is created for
+#[derive(Debug, Clone)]
+pub struct AuthorizationRequest {
+    pub scopes: Option<String>,
+    pub state: Option<String>,
+    pub ty: AuthorizationType,
+    /// The user must be asked for consent, even if they consented before
+    pub force_consent: bool,
+    /// Passed on to the ID token
+    pub nonce: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -26,7 +61,17 @@ pub struct OAuth2PendingAuthorizationUnauthorized {
     client_id: String,
     scopes: Option<String>,
     state: Option<String>,
+    /// The user whose password has been verified, but who has yet to complete a second factor
+    pending_user_id: Option<String>,
+    /// Passed on to the ID token
+    nonce: Option<String>,
     ty: AuthorizationType,
+    /// The user must be asked for consent, even if they consented before
+    force_consent: bool,
+    created_at: i64,
+    expires_at: i64,
+    /// Hash of the binding value kept in a cookie by the browser which started the authorization
+    browser_binding_hash: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -36,16 +81,39 @@ pub struct OAuth2PendingAuthorizationAuthorized {
     scopes: Option<String>,
     state: Option<String>,
     user_id: String,
+    nonce: Option<String>,
+    /// When the user last entered their credentials.
+    /// `None` for authorizations created before this was recorded.
+    auth_time: Option<i64>,
+    /// Whether the user completed a second factor, or used a passkey
+    multi_factor: bool,
     ty: AuthorizationType,
+    force_consent: bool,
+    created_at: i64,
+    expires_at: i64,
+    /// Hash of the binding value kept in a cookie by the browser which started the authorization
+    browser_binding_hash: Option<String>,
 }
 
-#[derive(FromRow)]
+#[derive(Clone, FromRow)]
 pub struct OAuth2AuthorizationCode {
     pub code: String,
     pub client_id: String,
     pub expires_at: i64,
     pub scopes: Option<String>,
     pub user_id: String,
+    /// When the code was exchanged for tokens. Codes can only be used once.
+    pub consumed_at: Option<i64>,
+    /// The access token the code was exchanged for
+    pub access_token: Option<String>,
+    /// The refresh token the code was exchanged for
+    pub refresh_token: Option<String>,
+    /// The `nonce` of the authorization request
+    pub nonce: Option<String>,
+    /// When the user last entered their credentials
+    pub auth_time: Option<i64>,
+    /// Whether the user completed a second factor, or used a passkey
+    pub multi_factor: bool,
 }
 
 
@@ -56,7 +124,16 @@ struct _OAuth2PendingAuthorization {
     scopes: Option<String>,
     state: Option<String>,
     user_id: Option<String>,
+    pending_user_id: Option<String>,
+    nonce: Option<String>,
+    auth_time: Option<i64>,
+    multi_factor: bool,
     ty: AuthorizationType,
+    force_consent: bool,
+    created_at: i64,
+    expires_at: i64,
+    /// Hash of the binding value kept in a cookie by the browser which started the authorization
+    browser_binding_hash: Option<String>,
 }
 
 #[derive(Clone, Debug, FromRow)]
@@ -67,14 +144,22 @@ pub struct AccessToken {
     pub issued_at: i64,
     pub user_id: String,
     pub scopes: Option<String>,
+    /// The refresh token this token was issued with, if it was
+    pub refresh_token: Option<String>,
 }
 
-#[derive(FromRow)]
+#[derive(Clone, FromRow)]
 pub struct RefreshToken {
     pub token: String,
     pub client_id: String,
     pub user_id: String,
     pub scopes: Option<String>,
+    /// `None` if the token never expires
+    pub expires_at: Option<i64>,
+    /// When the user last entered their credentials, for the authorization the token was issued for
+    pub auth_time: Option<i64>,
+    /// Whether the user completed a second factor, or used a passkey
+    pub multi_factor: bool,
 }
 
 
@@ -137,6 +222,74 @@ impl OAuth2PendingAuthorization {
             Self::Unauthorized(v) => &v.scopes,
         }
     }
+
+    /// The user who passed the first login step, if the authorization is waiting on a second factor
+    pub fn pending_user_id(&self) -> Option<&String> {
+        match self {
+            Self::Authorized(_) => None,
+            Self::Unauthorized(v) => v.pending_user_id.as_ref(),
+        }
+    }
+
+    /// Whether the user must be asked for consent, even if they consented before
+    pub fn force_consent(&self) -> bool {
+        match self {
+            Self::Authorized(v) => v.force_consent,
+            Self::Unauthorized(v) => v.force_consent,
+        }
+    }
+
+    pub fn nonce(&self) -> &Option<String> {
+        match self {
+            Self::Authorized(v) => &v.nonce,
+            Self::Unauthorized(v) => &v.nonce,
+        }
+    }
+
+    /// The user who completed the login, if any
+    pub fn user_id(&self) -> Option<&String> {
+        match self {
+            Self::Authorized(v) => Some(&v.user_id),
+            Self::Unauthorized(_) => None,
+        }
+    }
+
+    pub fn created_at(&self) -> i64 {
+        match self {
+            Self::Authorized(v) => v.created_at,
+            Self::Unauthorized(v) => v.created_at,
+        }
+    }
+
+    pub fn expires_at(&self) -> i64 {
+        match self {
+            Self::Authorized(v) => v.expires_at,
+            Self::Unauthorized(v) => v.expires_at,
+        }
+    }
+
+    /// Expired authorizations can no longer be logged in to or approved
+    pub fn is_expired(&self) -> bool {
+        OffsetDateTime::now_utc().unix_timestamp() >= self.expires_at()
+    }
+
+    /// Generate a value to bind pending authorizations to a browser with
+    pub fn generate_browser_binding() -> String {
+        generate_string(48)
+    }
+
+    /// Whether the authorization was started by the browser holding `binding`.
+    /// Authorizations created before browser binding existed are bound to no browser.
+    pub fn is_bound_to(&self, binding: &str) -> bool {
+        self.browser_binding_hash() == Some(&hash_secret(binding))
+    }
+
+    fn browser_binding_hash(&self) -> Option<&String> {
+        match self {
+            Self::Authorized(v) => v.browser_binding_hash.as_ref(),
+            Self::Unauthorized(v) => v.browser_binding_hash.as_ref(),
+        }
+    }
 }
 
 impl OAuth2Client {
@@ -156,8 +309,8 @@ impl OAuth2Client {
         generate_string(16)
     }
 
-    fn generate_authorization_code_expiry() -> i64 {
-        (OffsetDateTime::now_utc() + Duration::minutes(10)).unix_timestamp()
+    fn generate_pending_authorization_expiry(lifetimes: &TokenLifetimes) -> i64 {
+        OffsetDateTime::now_utc().unix_timestamp() + lifetimes.pending_authorization
     }
 
     fn generate_access_token() -> String {
@@ -168,8 +321,95 @@ impl OAuth2Client {
         generate_string(32)
     }
 
-    fn generate_access_token_expiry() -> i64 {
-        (OffsetDateTime::now_utc() + Duration::hours(1)).unix_timestamp()
+    fn generate_authorization_code_expiry(&self, lifetimes: &TokenLifetimes) -> i64 {
+        let lifetime = self.authorization_code_lifetime.unwrap_or(lifetimes.authorization_code);
+        OffsetDateTime::now_utc().unix_timestamp() + lifetime
+    }
+
+    fn generate_access_token_expiry(&self, lifetimes: &TokenLifetimes) -> i64 {
+        let lifetime = self.access_token_lifetime.unwrap_or(lifetimes.access_token);
+        OffsetDateTime::now_utc().unix_timestamp() + lifetime
+    }
+
+    fn generate_refresh_token_expiry(&self, lifetimes: &TokenLifetimes) -> Option<i64> {
+        let lifetime = self.refresh_token_lifetime.or(lifetimes.refresh_token)?;
+        Some(OffsetDateTime::now_utc().unix_timestamp() + lifetime)
+    }
+
+    /// Create a pending authorization for the client, without storing it
+    pub(crate) fn build_pending_authorization(
+        &self,
+        request: AuthorizationRequest,
+        browser_binding: &str,
+        lifetimes: &TokenLifetimes,
+    ) -> OAuth2PendingAuthorization {
+        OAuth2PendingAuthorization::Unauthorized(OAuth2PendingAuthorizationUnauthorized {
+            id: Self::generate_pending_authorization_id(),
+            client_id: self.client_id.clone(),
+            scopes: request.scopes,
+            state: request.state,
+            pending_user_id: None,
+            nonce: request.nonce,
+            ty: request.ty,
+            force_consent: request.force_consent,
+            created_at: OffsetDateTime::now_utc().unix_timestamp(),
+            expires_at: Self::generate_pending_authorization_expiry(lifetimes),
+            browser_binding_hash: Some(hash_secret(browser_binding)),
+        })
+    }
+
+    /// Create an authorization code for an authorized pending authorization, without storing it
+    pub(crate) fn build_authorization_code(
+        &self,
+        pending: OAuth2PendingAuthorization,
+        lifetimes: &TokenLifetimes,
+    ) -> std::result::Result<OAuth2AuthorizationCode, OAuth2AuthorizationCodeCreationError> {
+        let pending = match pending {
+            OAuth2PendingAuthorization::Authorized(v) => v,
+            OAuth2PendingAuthorization::Unauthorized(_) => {
+                return Err(OAuth2AuthorizationCodeCreationError::Unauthorized)
+            }
+        };
+
+        Ok(OAuth2AuthorizationCode {
+            code: Self::generate_authorization_code(),
+            client_id: self.client_id.clone(),
+            expires_at: self.generate_authorization_code_expiry(lifetimes),
+            scopes: pending.scopes,
+            user_id: pending.user_id,
+            consumed_at: None,
+            access_token: None,
+            refresh_token: None,
+            nonce: pending.nonce,
+            auth_time: pending.auth_time,
+            multi_factor: pending.multi_factor,
+        })
+    }
+
+    /// Create an access token for the client, without storing it
+    pub(crate) fn build_access_token(&self, user_id: String, scopes: Option<String>, lifetimes: &TokenLifetimes) -> AccessToken {
+        AccessToken {
+            token: Self::generate_access_token(),
+            client_id: self.client_id.clone(),
+            expires_at: self.generate_access_token_expiry(lifetimes),
+            issued_at: OffsetDateTime::now_utc().unix_timestamp(),
+            user_id,
+            scopes,
+            refresh_token: None,
+        }
+    }
+
+    /// Create a refresh token for the client to exchange the authorization code for, without storing it
+    pub(crate) fn build_refresh_token(&self, code: &OAuth2AuthorizationCode, lifetimes: &TokenLifetimes) -> RefreshToken {
+        RefreshToken {
+            token: Self::generate_refresh_token(),
+            client_id: self.client_id.clone(),
+            user_id: code.user_id.clone(),
+            scopes: code.scopes.clone(),
+            expires_at: self.generate_refresh_token_expiry(lifetimes),
+            auth_time: code.auth_time,
+            multi_factor: code.multi_factor,
+        }
     }
 
     pub async fn new(
@@ -177,18 +417,32 @@ impl OAuth2Client {
         name: String,
         redirect_uri: String,
         internal: bool,
+        trusted: bool,
     ) -> Result<Self> {
         let client_id = Self::generate_client_id();
         let client_secret = Self::generate_client_secret();
 
-        sqlx::query("INSERT INTO oauth2_clients (name, redirect_uri, client_id, client_secret, is_internal) VALUES (?, ?, ?, ?, ?)")
-            .bind(&name)
-            .bind(&redirect_uri)
-            .bind(&client_id)
-            .bind(&client_secret)
-            .bind(internal)
-            .execute(&**driver)
-            .await?;
+        with_pool!(driver, |pool, sql| {
+            let mut tx = pool.begin().await?;
+
+            sqlx::query(&sql("INSERT INTO oauth2_clients (name, redirect_uri, client_id, client_secret, is_internal, is_trusted) VALUES (?, ?, ?, ?, ?, ?)"))
+                .bind(&name)
+                .bind(&redirect_uri)
+                .bind(&client_id)
+                .bind(&client_secret)
+                .bind(internal)
+                .bind(trusted)
+                .execute(&mut *tx)
+                .await?;
+
+            // New clients may request the default scopes
+            sqlx::query(&sql("INSERT INTO oauth2_client_scopes (client_id, scope) SELECT ?, name FROM scopes WHERE is_default = TRUE"))
+                .bind(&client_id)
+                .execute(&mut *tx)
+                .await?;
+
+            tx.commit().await?;
+        });
 
         Ok(Self {
             name,
@@ -196,106 +450,280 @@ impl OAuth2Client {
             client_id,
             client_secret,
             is_internal: internal,
+            is_trusted: trusted,
+            backchannel_logout_uri: None,
+            access_token_lifetime: None,
+            authorization_code_lifetime: None,
+            refresh_token_lifetime: None,
         })
     }
 
     pub async fn list(driver: &Database) -> Result<Vec<Self>> {
-        Ok(sqlx::query_as("SELECT * FROM oauth2_clients")
-            .fetch_all(&**driver)
-            .await?)
+        with_pool!(driver, |pool, sql| {
+            sqlx::query_as(&sql("SELECT * FROM oauth2_clients"))
+                .fetch_all(pool)
+                .await
+        })
     }
 
     pub async fn delete(self, driver: &Database) -> Result<()> {
-        sqlx::query("DELETE FROM oauth2_clients WHERE client_id = ?")
-            .bind(self.client_id)
-            .execute(&**driver)
-            .await?;
+        with_pool!(driver, |pool, sql| {
+            let mut tx = pool.begin().await?;
+
+            for query in [
+                "DELETE FROM oauth2_client_scopes WHERE client_id = ?",
+                "DELETE FROM oauth2_client_post_logout_redirect_uris WHERE client_id = ?",
+                "DELETE FROM backchannel_logout_deliveries WHERE client_id = ?",
+                "DELETE FROM oauth2_consent_scopes WHERE client_id = ?",
+                "DELETE FROM oauth2_consents WHERE client_id = ?",
+            ] {
+                sqlx::query(&sql(query))
+                    .bind(&self.client_id)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+
+            sqlx::query(&sql("DELETE FROM oauth2_clients WHERE client_id = ?"))
+                .bind(&self.client_id)
+                .execute(&mut *tx)
+                .await?;
+
+            tx.commit().await?;
+        });
+        Ok(())
+    }
+
+    pub async fn set_trusted(&mut self, driver: &Database, trusted: bool) -> Result<()> {
+        with_pool!(driver, |pool, sql| {
+            sqlx::query(&sql("UPDATE oauth2_clients SET is_trusted = ? WHERE client_id = ?"))
+                .bind(trusted)
+                .bind(&self.client_id)
+                .execute(pool)
+                .await
+                .map(|result| result.rows_affected())
+        })?;
+
+        self.is_trusted = trusted;
+        Ok(())
+    }
+
+    /// All clients which hold tokens for the user
+    pub async fn list_by_user_tokens(driver: &Database, user_id: &str) -> Result<Vec<Self>> {
+        with_pool!(driver, |pool, sql| {
+            sqlx::query_as(&sql("SELECT * FROM oauth2_clients WHERE client_id IN (SELECT client_id FROM oauth2_access_tokens WHERE user_id = ? UNION SELECT client_id FROM oauth2_refresh_tokens WHERE user_id = ?)"))
+                .bind(user_id)
+                .bind(user_id)
+                .fetch_all(pool)
+                .await
+        })
+    }
+
+    pub async fn set_backchannel_logout_uri(&mut self, driver: &Database, uri: Option<String>) -> Result<()> {
+        with_pool!(driver, |pool, sql| {
+            sqlx::query(&sql("UPDATE oauth2_clients SET backchannel_logout_uri = ? WHERE client_id = ?"))
+                .bind(&uri)
+                .bind(&self.client_id)
+                .execute(pool)
+                .await
+                .map(|result| result.rows_affected())
+        })?;
+
+        self.backchannel_logout_uri = uri;
+        Ok(())
+    }
+
+    /// Set the overrides of the configured token and code lifetimes. `None` removes an override.
+    pub async fn set_token_lifetimes(
+        &mut self,
+        driver: &Database,
+        access_token: Option<i64>,
+        authorization_code: Option<i64>,
+        refresh_token: Option<i64>,
+    ) -> Result<()> {
+        with_pool!(driver, |pool, sql| {
+            sqlx::query(&sql("UPDATE oauth2_clients SET access_token_lifetime = ?, authorization_code_lifetime = ?, refresh_token_lifetime = ? WHERE client_id = ?"))
+                .bind(access_token)
+                .bind(authorization_code)
+                .bind(refresh_token)
+                .bind(&self.client_id)
+                .execute(pool)
+                .await
+                .map(|result| result.rows_affected())
+        })?;
+
+        self.access_token_lifetime = access_token;
+        self.authorization_code_lifetime = authorization_code;
+        self.refresh_token_lifetime = refresh_token;
+        Ok(())
+    }
+
+    /// The URIs the client may have the user redirected to after logging out
+    pub async fn list_post_logout_redirect_uris(&self, driver: &Database) -> Result<Vec<String>> {
+        with_pool!(driver, |pool, sql| {
+            sqlx::query_scalar(&sql("SELECT uri FROM oauth2_client_post_logout_redirect_uris WHERE client_id = ?"))
+                .bind(&self.client_id)
+                .fetch_all(pool)
+                .await
+        })
+    }
+
+    /// Replace the URIs the client may have the user redirected to after logging out
+    pub async fn set_post_logout_redirect_uris(&self, driver: &Database, uris: &HashSet<String>) -> Result<()> {
+        with_pool!(driver, |pool, sql| {
+            let mut tx = pool.begin().await?;
+
+            sqlx::query(&sql("DELETE FROM oauth2_client_post_logout_redirect_uris WHERE client_id = ?"))
+                .bind(&self.client_id)
+                .execute(&mut *tx)
+                .await?;
+
+            for uri in uris {
+                sqlx::query(&sql("INSERT INTO oauth2_client_post_logout_redirect_uris (client_id, uri) VALUES (?, ?)"))
+                    .bind(&self.client_id)
+                    .bind(uri)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+
+            tx.commit().await?;
+        });
+        Ok(())
+    }
+
+    /// Delete all access and refresh tokens the client holds for the user
+    pub async fn revoke_tokens_for_user(&self, driver: &Database, user_id: &str) -> Result<()> {
+        with_pool!(driver, |pool, sql| {
+            let mut tx = pool.begin().await?;
+
+            for query in [
+                "DELETE FROM oauth2_access_tokens WHERE client_id = ? AND user_id = ?",
+                "DELETE FROM oauth2_refresh_tokens WHERE client_id = ? AND user_id = ?",
+            ] {
+                sqlx::query(&sql(query))
+                    .bind(&self.client_id)
+                    .bind(user_id)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+
+            tx.commit().await?;
+        });
+        Ok(())
+    }
+
+    /// The scopes the client may request
+    pub async fn list_allowed_scopes(&self, driver: &Database) -> Result<Vec<String>> {
+        with_pool!(driver, |pool, sql| {
+            sqlx::query_scalar(&sql("SELECT scope FROM oauth2_client_scopes WHERE client_id = ?"))
+                .bind(&self.client_id)
+                .fetch_all(pool)
+                .await
+        })
+    }
+
+    pub async fn allow_scope(&self, driver: &Database, scope: &str) -> Result<()> {
+        with_pool!(driver, |pool, sql| {
+            sqlx::query(&sql("INSERT INTO oauth2_client_scopes (client_id, scope) VALUES (?, ?)"))
+                .bind(&self.client_id)
+                .bind(scope)
+                .execute(pool)
+                .await
+                .map(|result| result.rows_affected())
+        })?;
+
+        Ok(())
+    }
+
+    pub async fn disallow_scope(&self, driver: &Database, scope: &str) -> Result<()> {
+        with_pool!(driver, |pool, sql| {
+            sqlx::query(&sql("DELETE FROM oauth2_client_scopes WHERE client_id = ? AND scope = ?"))
+                .bind(&self.client_id)
+                .bind(scope)
+                .execute(pool)
+                .await
+                .map(|result| result.rows_affected())
+        })?;
+
         Ok(())
     }
 
     pub async fn get_by_client_id(driver: &Database, client_id: &str) -> Result<Option<Self>> {
-        Ok(
-            sqlx::query_as("SELECT * FROM oauth2_clients WHERE client_id = ?")
+        with_pool!(driver, |pool, sql| {
+            sqlx::query_as(&sql("SELECT * FROM oauth2_clients WHERE client_id = ?"))
                 .bind(client_id)
-                .fetch_optional(&**driver)
-                .await?,
-        )
+                .fetch_optional(pool)
+                .await
+        })
     }
 
     pub async fn new_pending_authorization(
         &self,
         driver: &Database,
-        scopes: Option<String>,
-        state: Option<String>,
-        ty: AuthorizationType,
+        request: AuthorizationRequest,
+        browser_binding: &str,
+        lifetimes: &TokenLifetimes,
     ) -> Result<OAuth2PendingAuthorization> {
-        let id = Self::generate_pending_authorization_id();
-        sqlx::query("INSERT INTO oauth2_pending_authorizations (id, client_id, scopes, state, ty) VALUES (?, ?, ?, ?, ?)")
-            .bind(&id)
-            .bind(&self.client_id)
-            .bind(&scopes)
-            .bind(&state)
-            .bind(&ty)
-            .execute(&**driver)
-            .await?;
-
-        Ok(OAuth2PendingAuthorization::Unauthorized(
-            OAuth2PendingAuthorizationUnauthorized {
-                id,
-                client_id: self.client_id.clone(),
-                scopes,
-                state,
-                ty,
-            },
-        ))
+        let pending = self.build_pending_authorization(request, browser_binding, lifetimes);
+        with_pool!(driver, |pool, sql| {
+            sqlx::query(&sql("INSERT INTO oauth2_pending_authorizations (id, client_id, scopes, state, nonce, ty, force_consent, created_at, expires_at, browser_binding_hash) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"))
+                .bind(pending.id())
+                .bind(pending.client_id())
+                .bind(pending.scopes())
+                .bind(pending.state())
+                .bind(pending.nonce())
+                .bind(pending.ty())
+                .bind(pending.force_consent())
+                .bind(pending.created_at())
+                .bind(pending.expires_at())
+                .bind(pending.browser_binding_hash())
+                .execute(pool)
+                .await
+                .map(|result| result.rows_affected())
+        })?;
+
+        Ok(pending)
     }
 
     pub async fn new_authorization_code(
         &self,
         driver: &Database,
         pending: OAuth2PendingAuthorization,
+        lifetimes: &TokenLifetimes,
     ) -> std::result::Result<OAuth2AuthorizationCode, OAuth2AuthorizationCodeCreationError> {
-        let pending = match pending {
-            OAuth2PendingAuthorization::Authorized(v) => v,
-            OAuth2PendingAuthorization::Unauthorized(_) => {
-                return Err(OAuth2AuthorizationCodeCreationError::Unauthorized)
-            }
-        };
-
-        let code = Self::generate_authorization_code();
-        let expires_at = Self::generate_authorization_code_expiry();
-
-        let mut tx = driver.begin().await?;
-
-        sqlx::query("INSERT INTO oauth2_authorization_codes (client_id, code, expires_at, scopes, user_id) VALUES (?, ?, ?, ?, ?)")
-            .bind(&self.client_id)
-            .bind(&code)
-            .bind(expires_at)
-            .bind(&pending.scopes)
-            .bind(&pending.user_id)
-            .execute(&mut *tx)
-            .await?;
-
-        sqlx::query("DELETE FROM oauth2_pending_authorizations WHERE id = ? ")
-            .bind(&pending.id)
-            .execute(&mut *tx)
-            .await?;
-
-        tx.commit().await?;
-
-        Ok(OAuth2AuthorizationCode {
-            client_id: self.client_id.clone(),
-            code,
-            scopes: pending.scopes.clone(),
-            expires_at,
-            user_id: pending.user_id,
-        })
+        let pending_id = pending.id().clone();
+        let code = self.build_authorization_code(pending, lifetimes)?;
+
+        with_pool!(driver, |pool, sql| {
+            let mut tx = pool.begin().await?;
+
+            sqlx::query(&sql("INSERT INTO oauth2_authorization_codes (client_id, code, expires_at, scopes, user_id, nonce, auth_time, multi_factor) VALUES (?, ?, ?, ?, ?, ?, ?, ?)"))
+                .bind(&code.client_id)
+                .bind(&code.code)
+                .bind(code.expires_at)
+                .bind(&code.scopes)
+                .bind(&code.user_id)
+                .bind(&code.nonce)
+                .bind(code.auth_time)
+                .bind(code.multi_factor)
+                .execute(&mut *tx)
+                .await?;
+
+            sqlx::query(&sql("DELETE FROM oauth2_pending_authorizations WHERE id = ? "))
+                .bind(&pending_id)
+                .execute(&mut *tx)
+                .await?;
+
+            tx.commit().await?;
+        });
+
+        Ok(code)
     }
 
     pub async fn new_access_token(
         &self,
         driver: &Database,
         authorization: OAuth2PendingAuthorization,
+        lifetimes: &TokenLifetimes,
     ) -> std::result::Result<AccessToken, OAuth2AuthorizationCodeCreationError> {
         let authorization = match authorization {
             OAuth2PendingAuthorization::Authorized(v) => v,
@@ -304,134 +732,125 @@ impl OAuth2Client {
             }
         };
 
-        let atoken = Self::generate_access_token();
-        let expires_at = Self::generate_access_token_expiry();
-        let issued_at = OffsetDateTime::now_utc().unix_timestamp();
-
-        let mut tx = driver.begin().await?;
-
-        sqlx::query("INSERT INTO oauth2_access_tokens (token, client_id, expires_at, issued_at, user_id, scopes) VALUES (?, ?, ?, ?, ?, ?)")
-            .bind(&atoken)
-            .bind(&self.client_id)
-            .bind(expires_at)
-            .bind(issued_at)
-            .bind(&authorization.user_id)
-            .bind(&authorization.scopes)
-            .execute(&mut *tx)
-            .await?;
-
-        sqlx::query("DELETE FROM oauth2_pending_authorizations WHERE id = ? ")
-            .bind(&authorization.id)
-            .execute(&mut *tx)
-            .await?;
-
-        tx.commit().await?;
-
-        Ok(AccessToken {
-            token: atoken,
-            issued_at,
-            expires_at,
-            user_id: authorization.user_id,
-            scopes: authorization.scopes,
-            client_id: self.client_id.clone(),
-        })
+        let atoken = self.build_access_token(authorization.user_id, authorization.scopes, lifetimes);
+
+        with_pool!(driver, |pool, sql| {
+            let mut tx = pool.begin().await?;
+
+            sqlx::query(&sql("INSERT INTO oauth2_access_tokens (token, client_id, expires_at, issued_at, user_id, scopes) VALUES (?, ?, ?, ?, ?, ?)"))
+                .bind(&atoken.token)
+                .bind(&atoken.client_id)
+                .bind(atoken.expires_at)
+                .bind(atoken.issued_at)
+                .bind(&atoken.user_id)
+                .bind(&atoken.scopes)
+                .execute(&mut *tx)
+                .await?;
+
+            sqlx::query(&sql("DELETE FROM oauth2_pending_authorizations WHERE id = ? "))
+                .bind(&authorization.id)
+                .execute(&mut *tx)
+                .await?;
+
+            tx.commit().await?;
+        });
+
+        Ok(atoken)
     }
 
+    /// Exchange an authorization code for an access and refresh token.
+    /// The code is marked as consumed, rather than removed, so a replay can be detected.
+    /// Returns `None` if the code has already been consumed.
     pub async fn new_token_pair(
         &self,
         driver: &Database,
         authorization: OAuth2AuthorizationCode,
-    ) -> Result<(AccessToken, RefreshToken)> {
-        let atoken = Self::generate_access_token();
-        let rtoken = Self::generate_refresh_token();
-        let expires_at = Self::generate_access_token_expiry();
-        let issued_at = OffsetDateTime::now_utc().unix_timestamp();
-
-        let mut tx = driver.begin().await?;
-
-        // Access token
-        sqlx::query("INSERT INTO oauth2_access_tokens (token, client_id, expires_at, issued_at, user_id, scopes) VALUES (?, ?, ?, ?, ?, ?)")
-            .bind(&atoken)
-            .bind(&self.client_id)
-            .bind(expires_at)
-            .bind(issued_at)
-            .bind(&authorization.user_id)
-            .bind(&authorization.scopes)
-            .execute(&mut *tx)
-            .await?;
-
-        // Refresh token
-        sqlx::query("INSERT INTO oauth2_refresh_tokens (token, client_id, user_id, scopes) VALUES (?, ?, ?, ?)")
-            .bind(&rtoken)
-            .bind(&self.client_id)
-            .bind(&authorization.user_id)
-            .bind(&authorization.scopes)
-            .execute(&mut *tx)
-            .await?;
-
-        // Remove authorization
-        sqlx::query("DELETE FROM oauth2_authorization_codes WHERE code = ?")
-            .bind(&authorization.code)
-            .execute(&mut *tx)
-            .await?;
-
-        tx.commit().await?;
-
-        Ok((
-            AccessToken {
-                token: atoken,
-                client_id: self.client_id.clone(),
-                expires_at,
-                issued_at,
-                user_id: authorization.user_id.clone(),
-                scopes: authorization.scopes.clone(),
-            },
-            RefreshToken {
-                token: rtoken,
-                client_id: self.client_id.clone(),
-                user_id: authorization.user_id.clone(),
-                scopes: authorization.scopes.clone(),
-            },
-        ))
+        lifetimes: &TokenLifetimes,
+    ) -> Result<Option<(AccessToken, RefreshToken)>> {
+        let atoken = self.build_access_token(authorization.user_id.clone(), authorization.scopes.clone(), lifetimes);
+        let rtoken = self.build_refresh_token(&authorization, lifetimes);
+
+        with_pool!(driver, |pool, sql| {
+            let mut tx = pool.begin().await?;
+
+            // Claim the code first, so concurrent redemptions can't both succeed
+            let claimed = sqlx::query(&sql("UPDATE oauth2_authorization_codes SET consumed_at = ?, access_token = ?, refresh_token = ? WHERE code = ? AND consumed_at IS NULL"))
+                .bind(atoken.issued_at)
+                .bind(&atoken.token)
+                .bind(&rtoken.token)
+                .bind(&authorization.code)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+
+            if claimed == 0 {
+                return Ok(None);
+            }
+
+            // Access token
+            sqlx::query(&sql("INSERT INTO oauth2_access_tokens (token, client_id, expires_at, issued_at, user_id, scopes) VALUES (?, ?, ?, ?, ?, ?)"))
+                .bind(&atoken.token)
+                .bind(&atoken.client_id)
+                .bind(atoken.expires_at)
+                .bind(atoken.issued_at)
+                .bind(&atoken.user_id)
+                .bind(&atoken.scopes)
+                .execute(&mut *tx)
+                .await?;
+
+            // Refresh token
+            sqlx::query(&sql("INSERT INTO oauth2_refresh_tokens (token, client_id, user_id, scopes, expires_at, auth_time, multi_factor) VALUES (?, ?, ?, ?, ?, ?, ?)"))
+                .bind(&rtoken.token)
+                .bind(&rtoken.client_id)
+                .bind(&rtoken.user_id)
+                .bind(&rtoken.scopes)
+                .bind(rtoken.expires_at)
+                .bind(rtoken.auth_time)
+                .bind(rtoken.multi_factor)
+                .execute(&mut *tx)
+                .await?;
+
+            tx.commit().await?;
+        });
+
+        Ok(Some((atoken, rtoken)))
     }
 
     pub async fn refresh_access_token(
         &self,
         driver: &Database,
         refresh_token: &RefreshToken,
+        lifetimes: &TokenLifetimes,
     ) -> Result<AccessToken> {
-        let atoken = Self::generate_access_token();
-        let expires_at = Self::generate_access_token_expiry();
-        let issued_at = OffsetDateTime::now_utc().unix_timestamp();
-
-        sqlx::query("INSERT INTO oauth2_access_tokens (token, client_id, expires_at, issued_at, user_id, scopes) VALUES (?, ?, ?, ?, ?, ?)")
-            .bind(&atoken)
-            .bind(&self.client_id)
-            .bind(expires_at)
-            .bind(&refresh_token.user_id)
-            .bind(&refresh_token.scopes)
-            .execute(&**driver)
-            .await?;
-
-        Ok(AccessToken {
-            token: atoken,
-            client_id: self.client_id.clone(),
-            scopes: refresh_token.scopes.clone(),
-            issued_at,
-            expires_at,
-            user_id: refresh_token.user_id.clone(),
-        })
+        let mut atoken = self.build_access_token(refresh_token.user_id.clone(), refresh_token.scopes.clone(), lifetimes);
+        atoken.refresh_token = Some(refresh_token.token.clone());
+
+        with_pool!(driver, |pool, sql| {
+            sqlx::query(&sql("INSERT INTO oauth2_access_tokens (token, client_id, expires_at, issued_at, user_id, scopes, refresh_token) VALUES (?, ?, ?, ?, ?, ?, ?)"))
+                .bind(&atoken.token)
+                .bind(&atoken.client_id)
+                .bind(atoken.expires_at)
+                .bind(atoken.issued_at)
+                .bind(&atoken.user_id)
+                .bind(&atoken.scopes)
+                .bind(&atoken.refresh_token)
+                .execute(pool)
+                .await
+                .map(|result| result.rows_affected())
+        })?;
+
+        Ok(atoken)
     }
 }
 
 impl AccessToken {
     pub async fn get_by_token(driver: &Database, token: &str) -> Result<Option<Self>> {
-        Ok(
-            sqlx::query_as("SELECT * FROM oauth2_access_tokens WHERE token = ?")
+        with_pool!(driver, |pool, sql| {
+            sqlx::query_as(&sql("SELECT * FROM oauth2_access_tokens WHERE token = ?"))
                 .bind(token)
-                .fetch_optional(&**driver)
-                .await?,
-        )
+                .fetch_optional(pool)
+                .await
+        })
     }
 
     pub async fn get_with_validation(
@@ -440,15 +859,17 @@ impl AccessToken {
         client: &OAuth2Client,
     ) -> Result<Option<Self>> {
         Ok(
-            sqlx::query_as("SELECT * FROM oauth2_access_tokens WHERE token = ? AND client_id = ?")
-                .bind(token)
-                .bind(&client.client_id)
-                .fetch_optional(&**driver)
-                .await?
+            with_pool!(driver, |pool, sql| {
+                sqlx::query_as(&sql("SELECT * FROM oauth2_access_tokens WHERE token = ? AND client_id = ?"))
+                    .bind(token)
+                    .bind(&client.client_id)
+                    .fetch_optional(pool)
+                    .await
+            })?
                 // Only valid if the token hasn't expired yet
                 .map(|token: Self| {
                     let valid = OffsetDateTime::now_utc().unix_timestamp() < token.expires_at;
-                    valid.then(|| token)
+                    valid.then_some(token)
                 })
                 .unwrap_or(None), // No token found for the client --> not valid
         )
@@ -464,12 +885,17 @@ impl AccessToken {
 
 impl RefreshToken {
     pub async fn get_by_token(driver: &Database, token: &str) -> Result<Option<RefreshToken>> {
-        Ok(
-            sqlx::query_as("SELECT * FROM oauth2_refresh_tokens WHERE token = ?")
+        with_pool!(driver, |pool, sql| {
+            sqlx::query_as(&sql("SELECT * FROM oauth2_refresh_tokens WHERE token = ?"))
                 .bind(token)
-                .fetch_optional(&**driver)
-                .await?,
-        )
+                .fetch_optional(pool)
+                .await
+        })
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| OffsetDateTime::now_utc().unix_timestamp() >= expires_at)
     }
 }
 
@@ -479,58 +905,146 @@ impl OAuth2PendingAuthorization {
         id: &str,
     ) -> Result<Option<OAuth2PendingAuthorization>> {
         Ok(
-            sqlx::query_as("SELECT * FROM oauth2_pending_authorizations WHERE id = ?")
-                .bind(id)
-                .fetch_optional(&**driver)
-                .await?
+            with_pool!(driver, |pool, sql| {
+                sqlx::query_as(&sql("SELECT * FROM oauth2_pending_authorizations WHERE id = ?"))
+                    .bind(id)
+                    .fetch_optional(pool)
+                    .await
+            })?
                 .map(|v: _OAuth2PendingAuthorization| OAuth2PendingAuthorization::from(v)),
         )
     }
 
+    /// Give authorizations created before they could expire the configured lifetime.
+    /// The migration adding the expiry can't, as it does not know the configuration.
+    pub async fn backfill_expiry(driver: &Database, lifetimes: &TokenLifetimes) -> Result<u64> {
+        with_pool!(driver, |pool, sql| {
+            sqlx::query(&sql("UPDATE oauth2_pending_authorizations SET expires_at = created_at + ? WHERE expires_at = 0"))
+                .bind(lifetimes.pending_authorization)
+                .execute(pool)
+                .await
+                .map(|result| result.rows_affected())
+        })
+    }
+
+    /// Mark the authorization as authorized by `user_id`,
+    /// who last entered their credentials at `auth_time`.
     pub async fn set_user_id(
         self,
         driver: &Database,
         user_id: &str,
+        auth_time: i64,
+        multi_factor: bool,
     ) -> std::result::Result<Self, OAuth2PendingAuthorizationSetEspoIdError> {
-        let id = match &self {
-            Self::Unauthorized(v) => &v.id,
+        let new_self = self.into_authorized(user_id, auth_time, multi_factor)?;
+
+        with_pool!(driver, |pool, sql| {
+            sqlx::query(&sql("UPDATE oauth2_pending_authorizations SET user_id = ?, auth_time = ?, multi_factor = ? WHERE id = ?"))
+                .bind(user_id)
+                .bind(auth_time)
+                .bind(multi_factor)
+                .bind(new_self.id())
+                .execute(pool)
+                .await
+                .map(|result| result.rows_affected())
+        })?;
+
+        Ok(new_self)
+    }
+
+    /// Mark the authorization as authorized by `user_id`, without storing it
+    pub(crate) fn into_authorized(self, user_id: &str, auth_time: i64, multi_factor: bool) -> std::result::Result<Self, OAuth2PendingAuthorizationSetEspoIdError> {
+        match self {
+            Self::Unauthorized(v) => Ok(Self::Authorized(OAuth2PendingAuthorizationAuthorized {
+                id: v.id,
+                client_id: v.client_id,
+                user_id: user_id.to_string(),
+                nonce: v.nonce,
+                auth_time: Some(auth_time),
+                multi_factor,
+                state: v.state,
+                scopes: v.scopes,
+                ty: v.ty,
+                force_consent: v.force_consent,
+                created_at: v.created_at,
+                expires_at: v.expires_at,
+                browser_binding_hash: v.browser_binding_hash,
+            })),
+            Self::Authorized(_) => Err(OAuth2PendingAuthorizationSetEspoIdError::AlreadyAuthorized),
+        }
+    }
+
+    /// Record that `user_id` has passed the first login step.
+    /// The authorization remains unauthorized until [Self::set_user_id] is called after the second factor.
+    pub async fn set_pending_user_id(
+        self,
+        driver: &Database,
+        user_id: &str,
+    ) -> std::result::Result<Self, OAuth2PendingAuthorizationSetEspoIdError> {
+        let mut v = match self {
+            Self::Unauthorized(v) => v,
             Self::Authorized(_) => {
                 return Err(OAuth2PendingAuthorizationSetEspoIdError::AlreadyAuthorized)
             }
         };
 
-        sqlx::query("UPDATE oauth2_pending_authorizations SET user_id = ? WHERE id = ?")
-            .bind(user_id)
-            .bind(&id)
-            .execute(&**driver)
-            .await?;
-
-        let new_self = match self {
-            Self::Unauthorized(v) => {
-                Self::Authorized(OAuth2PendingAuthorizationAuthorized {
-                    id: v.id,
-                    client_id: v.client_id,
-                    user_id: user_id.to_string(),
-                    state: v.state,
-                    scopes: v.scopes,
-                    ty: v.ty,
-                })
-            }
-            Self::Authorized(_) => unreachable!(),
-        };
-
-        Ok(new_self)
+        with_pool!(driver, |pool, sql| {
+            sqlx::query(&sql("UPDATE oauth2_pending_authorizations SET pending_user_id = ? WHERE id = ?"))
+                .bind(user_id)
+                .bind(&v.id)
+                .execute(pool)
+                .await
+                .map(|result| result.rows_affected())
+        })?;
+
+        v.pending_user_id = Some(user_id.to_string());
+        Ok(Self::Unauthorized(v))
     }
 }
 
 impl OAuth2AuthorizationCode {
     pub async fn get_by_code(driver: &Database, code: &str) -> Result<Option<Self>> {
-        Ok(
-            sqlx::query_as("SELECT * FROM oauth2_authorization_codes WHERE code = ?")
+        with_pool!(driver, |pool, sql| {
+            sqlx::query_as(&sql("SELECT * FROM oauth2_authorization_codes WHERE code = ?"))
                 .bind(code)
-                .fetch_optional(&**driver)
-                .await?,
-        )
+                .fetch_optional(pool)
+                .await
+        })
+    }
+
+    pub fn is_consumed(&self) -> bool {
+        self.consumed_at.is_some()
+    }
+
+    /// Revoke the access and refresh token the code was exchanged for.
+    /// [RFC6749 Section 4.1.2](https://datatracker.ietf.org/doc/html/rfc6749#section-4.1.2)
+    pub async fn revoke_issued_tokens(&self, driver: &Database) -> Result<()> {
+        with_pool!(driver, |pool, sql| {
+            let mut tx = pool.begin().await?;
+
+            if let Some(access_token) = &self.access_token {
+                sqlx::query(&sql("DELETE FROM oauth2_access_tokens WHERE token = ?"))
+                    .bind(access_token)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+
+            if let Some(refresh_token) = &self.refresh_token {
+                // Including the access tokens issued with the refresh token since
+                sqlx::query(&sql("DELETE FROM oauth2_access_tokens WHERE refresh_token = ?"))
+                    .bind(refresh_token)
+                    .execute(&mut *tx)
+                    .await?;
+
+                sqlx::query(&sql("DELETE FROM oauth2_refresh_tokens WHERE token = ?"))
+                    .bind(refresh_token)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+
+            tx.commit().await?;
+        });
+        Ok(())
     }
 }
 
@@ -543,7 +1057,14 @@ impl From<_OAuth2PendingAuthorization> for OAuth2PendingAuthorization {
                 scopes: value.scopes,
                 state: value.state,
                 user_id,
+                nonce: value.nonce,
+                auth_time: value.auth_time,
+                multi_factor: value.multi_factor,
                 ty: value.ty,
+                force_consent: value.force_consent,
+                created_at: value.created_at,
+                expires_at: value.expires_at,
+                browser_binding_hash: value.browser_binding_hash,
             })
         } else {
             Self::Unauthorized(OAuth2PendingAuthorizationUnauthorized {
@@ -551,7 +1072,13 @@ impl From<_OAuth2PendingAuthorization> for OAuth2PendingAuthorization {
                 client_id: value.client_id,
                 scopes: value.scopes,
                 state: value.state,
+                pending_user_id: value.pending_user_id,
+                nonce: value.nonce,
                 ty: value.ty,
+                force_consent: value.force_consent,
+                created_at: value.created_at,
+                expires_at: value.expires_at,
+                browser_binding_hash: value.browser_binding_hash,
             })
         }
     }
@@ -0,0 +1,111 @@
+use std::collections::HashSet;
+
+use serde::Serialize;
+use sha1::{Digest, Sha1};
+use thiserror::Error;
+
+/// Requirements a new password must satisfy.
+/// Only enforced when a password is set, existing passwords keep working.
+#[derive(Debug, Clone)]
+pub struct PasswordPolicy {
+    /// Minimum length in characters
+    pub min_length: usize,
+    /// Maximum length in characters
+    pub max_length: usize,
+    /// Reject passwords equal to the user's email address
+    pub reject_email: bool,
+    pub breached_passwords: BreachedPasswords,
+}
+
+/// The rule a password failed to satisfy
+#[derive(Debug, Clone, Error, Serialize)]
+#[serde(tag = "rule", rename_all = "snake_case")]
+pub enum PasswordPolicyViolation {
+    #[error("Password must be at least {min_length} characters long")]
+    MinLength { min_length: usize },
+    #[error("Password must be at most {max_length} characters long")]
+    MaxLength { max_length: usize },
+    #[error("Password must not be equal to the email address")]
+    EqualsEmail,
+    #[error("Password appears in a list of breached passwords")]
+    Breached,
+}
+
+/// A set of known breached passwords, stored as SHA-1 hashes
+#[derive(Debug, Clone, Default)]
+pub struct BreachedPasswords(HashSet<[u8; 20]>);
+
+impl PasswordPolicy {
+    /// Check the password against all rules
+    ///
+    /// # Errors
+    ///
+    /// The first rule the password does not satisfy
+    pub fn check(&self, password: &str, email: &str) -> Result<(), PasswordPolicyViolation> {
+        let length = password.chars().count();
+        if length < self.min_length {
+            return Err(PasswordPolicyViolation::MinLength { min_length: self.min_length });
+        }
+
+        if length > self.max_length {
+            return Err(PasswordPolicyViolation::MaxLength { max_length: self.max_length });
+        }
+
+        if self.reject_email && password.trim().eq_ignore_ascii_case(email.trim()) {
+            return Err(PasswordPolicyViolation::EqualsEmail);
+        }
+
+        if self.breached_passwords.contains(password) {
+            return Err(PasswordPolicyViolation::Breached);
+        }
+
+        Ok(())
+    }
+}
+
+impl BreachedPasswords {
+    /// Parse a list of breached passwords, one per line.
+    /// Lines may either be a plaintext password, or a hex encoded SHA-1 hash
+    /// optionally followed by `:<count>`, as in the Have I Been Pwned password lists.
+    pub fn parse(list: &str) -> Self {
+        let hashes = list
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(|line| {
+                let candidate = line.split(':').next().unwrap_or(line);
+                Self::decode_hex(candidate).unwrap_or_else(|| Self::hash(line))
+            })
+            .collect();
+
+        Self(hashes)
+    }
+
+    pub fn contains(&self, password: &str) -> bool {
+        self.0.contains(&Self::hash(password))
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn hash(password: &str) -> [u8; 20] {
+        Sha1::digest(password).into()
+    }
+
+    fn decode_hex(input: &str) -> Option<[u8; 20]> {
+        if input.len() != 40 || !input.is_ascii() {
+            return None;
+        }
+
+        let mut bytes = [0_u8; 20];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&input[i * 2..i * 2 + 2], 16).ok()?;
+        }
+
+        Some(bytes)
+    }
+}
@@ -0,0 +1,158 @@
+use std::collections::HashSet;
+
+use sqlx::{FromRow, Result};
+use time::OffsetDateTime;
+
+use crate::driver::Database;
+use crate::{generate_string, hash_secret};
+use crate::with_pool;
+
+/// Prefix of every personal access token, to tell them apart from OAuth2 access tokens
+pub const TOKEN_PREFIX: &str = "mbp_";
+
+/// Don't write the last used timestamp more often than this, in seconds
+const LAST_USED_RESOLUTION: i64 = 60;
+
+/// A long-lived access token, created by a user rather than through an OAuth2 flow.
+/// Used for scripts and services. Only a hash of the token is stored.
+#[derive(Debug, Clone, FromRow)]
+pub struct PersonalAccessToken {
+    pub id: String,
+    pub name: String,
+    pub user_id: String,
+    pub scopes: Option<String>,
+    pub created_at: i64,
+    pub expires_at: Option<i64>,
+    pub last_used_at: Option<i64>,
+}
+
+impl PersonalAccessToken {
+    fn generate_id() -> String {
+        generate_string(32)
+    }
+
+    fn generate_token() -> String {
+        format!("{TOKEN_PREFIX}{}", generate_string(40))
+    }
+
+    /// Create a new token. Returns the token itself alongside its metadata,
+    /// the token can not be retrieved later.
+    pub async fn new(
+        driver: &Database,
+        user_id: &str,
+        name: String,
+        scopes: Option<String>,
+        expires_at: Option<i64>,
+    ) -> Result<(Self, String)> {
+        let id = Self::generate_id();
+        let token = Self::generate_token();
+        let token_hash = hash_secret(&token);
+        let created_at = OffsetDateTime::now_utc().unix_timestamp();
+
+        with_pool!(driver, |pool, sql| {
+            sqlx::query(&sql("INSERT INTO constant_access_tokens (id, name, token_hash, user_id, scopes, created_at, expires_at) VALUES (?, ?, ?, ?, ?, ?, ?)"))
+                .bind(&id)
+                .bind(&name)
+                .bind(&token_hash)
+                .bind(user_id)
+                .bind(&scopes)
+                .bind(created_at)
+                .bind(expires_at)
+                .execute(pool)
+                .await
+                .map(|result| result.rows_affected())
+        })?;
+
+        Ok((
+            Self {
+                id,
+                name,
+                user_id: user_id.to_string(),
+                scopes,
+                created_at,
+                expires_at,
+                last_used_at: None,
+            },
+            token,
+        ))
+    }
+
+    pub async fn list_by_user_id(driver: &Database, user_id: &str) -> Result<Vec<Self>> {
+        with_pool!(driver, |pool, sql| {
+            sqlx::query_as(&sql("SELECT * FROM constant_access_tokens WHERE user_id = ?"))
+                .bind(user_id)
+                .fetch_all(pool)
+                .await
+        })
+    }
+
+    pub async fn get_by_id(driver: &Database, id: &str) -> Result<Option<Self>> {
+        with_pool!(driver, |pool, sql| {
+            sqlx::query_as(&sql("SELECT * FROM constant_access_tokens WHERE id = ?"))
+                .bind(id)
+                .fetch_optional(pool)
+                .await
+        })
+    }
+
+    pub async fn get_by_name(driver: &Database, user_id: &str, name: &str) -> Result<Option<Self>> {
+        with_pool!(driver, |pool, sql| {
+            sqlx::query_as(&sql("SELECT * FROM constant_access_tokens WHERE user_id = ? AND name = ?"))
+                .bind(user_id)
+                .bind(name)
+                .fetch_optional(pool)
+                .await
+        })
+    }
+
+    /// Look up a token, only returning it if it has not expired
+    pub async fn get_valid_by_token(driver: &Database, token: &str) -> Result<Option<Self>> {
+        let token: Option<Self> = with_pool!(driver, |pool, sql| {
+            sqlx::query_as(&sql("SELECT * FROM constant_access_tokens WHERE token_hash = ?"))
+                .bind(hash_secret(token))
+                .fetch_optional(pool)
+                .await
+        })?;
+
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+        Ok(token.filter(|token| token.expires_at.map(|expires_at| now < expires_at).unwrap_or(true)))
+    }
+
+    /// Record that the token was used just now
+    pub async fn touch(&mut self, driver: &Database) -> Result<()> {
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+        if self.last_used_at.map(|last_used_at| now - last_used_at < LAST_USED_RESOLUTION).unwrap_or(false) {
+            return Ok(());
+        }
+
+        with_pool!(driver, |pool, sql| {
+            sqlx::query(&sql("UPDATE constant_access_tokens SET last_used_at = ? WHERE id = ?"))
+                .bind(now)
+                .bind(&self.id)
+                .execute(pool)
+                .await
+                .map(|result| result.rows_affected())
+        })?;
+
+        self.last_used_at = Some(now);
+        Ok(())
+    }
+
+    pub async fn delete(self, driver: &Database) -> Result<()> {
+        with_pool!(driver, |pool, sql| {
+            sqlx::query(&sql("DELETE FROM constant_access_tokens WHERE id = ?"))
+                .bind(&self.id)
+                .execute(pool)
+                .await
+                .map(|result| result.rows_affected())
+        })?;
+        Ok(())
+    }
+
+    pub fn scopes(&self) -> HashSet<String> {
+        self.scopes
+            .as_ref()
+            .map(|f| f.split(' ').map(|c| c.to_string()).collect::<HashSet<_>>())
+            .unwrap_or_default()
+    }
+}
@@ -0,0 +1,142 @@
+use sqlx::Result;
+use time::OffsetDateTime;
+
+use crate::driver::Database;
+use crate::login_throttle::{ThrottleKind, ThrottlePolicy};
+use crate::with_pool;
+
+/// Number of rows removed by [purge_expired]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PurgeCounts {
+    pub access_tokens: u64,
+    pub refresh_tokens: u64,
+    pub authorization_codes: u64,
+    pub pending_authorizations: u64,
+    pub webauthn_challenges: u64,
+    pub sessions: u64,
+    pub login_throttles: u64,
+}
+
+impl PurgeCounts {
+    pub fn total(&self) -> u64 {
+        self.access_tokens
+            + self.refresh_tokens
+            + self.authorization_codes
+            + self.pending_authorizations
+            + self.webauthn_challenges
+            + self.sessions
+            + self.login_throttles
+    }
+}
+
+/// Delete expired access tokens, refresh tokens, authorization codes, pending authorizations, WebAuthn challenges
+/// and single sign-on sessions, as well as login throttles which no longer hold back attempts under `throttle_policy`.
+/// Rows are deleted in batches of at most `batch_size`, so tables are never locked for long.
+pub async fn purge_expired(driver: &Database, batch_size: u32, throttle_policy: &ThrottlePolicy) -> Result<PurgeCounts> {
+    let now = OffsetDateTime::now_utc().unix_timestamp();
+
+    Ok(PurgeCounts {
+        access_tokens: purge_batched(driver, "oauth2_access_tokens", "token", "expires_at", now, batch_size).await?,
+        // Refresh tokens without an expiry are never selected, as `NULL < now` is not true
+        refresh_tokens: purge_batched(driver, "oauth2_refresh_tokens", "token", "expires_at", now, batch_size).await?,
+        authorization_codes: purge_batched(driver, "oauth2_authorization_codes", "code", "expires_at", now, batch_size).await?,
+        pending_authorizations: purge_batched(driver, "oauth2_pending_authorizations", "id", "expires_at", now, batch_size).await?,
+        webauthn_challenges: purge_batched(driver, "webauthn_challenges", "id", "expires_at", now, batch_size).await?,
+        sessions: purge_batched(driver, "sso_sessions", "id", "expires_at", now, batch_size).await?,
+        login_throttles: purge_login_throttles(driver, throttle_policy, now, batch_size).await?,
+    })
+}
+
+/// Delete the throttles whose failed attempts are forgotten, and which are not locked.
+/// They are keyed by kind and subject, so [purge_batched] does not apply.
+async fn purge_login_throttles(driver: &Database, policy: &ThrottlePolicy, now: i64, batch_size: u32) -> Result<u64> {
+    let cutoff = now - policy.reset_after;
+    let mut deleted = 0;
+
+    loop {
+        let keys: Vec<(ThrottleKind, String)> = with_pool!(driver, |pool, sql| {
+            sqlx::query_as(&sql("SELECT kind, subject FROM login_throttles WHERE last_failed_at < ? AND (locked_until IS NULL OR locked_until < ?) LIMIT ?"))
+                .bind(cutoff)
+                .bind(now)
+                .bind(i64::from(batch_size))
+                .fetch_all(pool)
+                .await
+        })?;
+
+        if keys.is_empty() {
+            break;
+        }
+
+        let conditions = vec!["(kind = ? AND subject = ?)"; keys.len()].join(" OR ");
+        let delete = format!("DELETE FROM login_throttles WHERE {conditions}");
+
+        deleted += with_pool!(driver, |pool, sql| {
+            let sql = sql(&delete);
+            let mut query = sqlx::query(&sql);
+            for (kind, subject) in &keys {
+                query = query.bind(*kind).bind(subject);
+            }
+
+            query
+                .execute(pool)
+                .await
+                .map(|result| result.rows_affected())
+        })?;
+
+        if keys.len() < batch_size as usize {
+            break;
+        }
+    }
+
+    Ok(deleted)
+}
+
+/// Delete all rows of `table` where `column` is before `cutoff`, one batch at a time.
+/// The keys of a batch are selected first, as MySQL does not support `LIMIT` in a subquery of a `DELETE`.
+async fn purge_batched(
+    driver: &Database,
+    table: &str,
+    key: &str,
+    column: &str,
+    cutoff: i64,
+    batch_size: u32,
+) -> Result<u64> {
+    let select = format!("SELECT {key} FROM {table} WHERE {column} < ? LIMIT ?");
+    let mut deleted = 0;
+
+    loop {
+        let keys: Vec<String> = with_pool!(driver, |pool, sql| {
+            sqlx::query_scalar(&sql(&select))
+                .bind(cutoff)
+                .bind(i64::from(batch_size))
+                .fetch_all(pool)
+                .await
+        })?;
+
+        if keys.is_empty() {
+            break;
+        }
+
+        let placeholders = vec!["?"; keys.len()].join(", ");
+        let delete = format!("DELETE FROM {table} WHERE {key} IN ({placeholders})");
+
+        deleted += with_pool!(driver, |pool, sql| {
+            let sql = sql(&delete);
+            let mut query = sqlx::query(&sql);
+            for key in &keys {
+                query = query.bind(key);
+            }
+
+            query
+                .execute(pool)
+                .await
+                .map(|result| result.rows_affected())
+        })?;
+
+        if keys.len() < batch_size as usize {
+            break;
+        }
+    }
+
+    Ok(deleted)
+}
@@ -0,0 +1,159 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+
+use sqlx::Result;
+
+use crate::oauth2_client::{
+    AccessToken, AuthorizationRequest, OAuth2AuthorizationCode, OAuth2AuthorizationCodeCreationError, OAuth2Client,
+    OAuth2PendingAuthorization, OAuth2PendingAuthorizationSetEspoIdError, RefreshToken, TokenLifetimes,
+};
+use crate::repository::{
+    AuthorizationCodeRepository, ClientRepository, PendingAuthorizationRepository, TokenRepository,
+};
+
+/// Storage which keeps everything in memory, and never fails.
+/// Intended for tests.
+#[derive(Default)]
+pub struct MemoryStorage {
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    clients: HashMap<String, OAuth2Client>,
+    pending_authorizations: HashMap<String, OAuth2PendingAuthorization>,
+    authorization_codes: HashMap<String, OAuth2AuthorizationCode>,
+    access_tokens: HashMap<String, AccessToken>,
+    refresh_tokens: HashMap<String, RefreshToken>,
+}
+
+impl MemoryStorage {
+    pub fn insert_client(&self, client: OAuth2Client) {
+        self.state().clients.insert(client.client_id.clone(), client);
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        // A panic while holding the lock can't leave the maps half updated
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl ClientRepository for MemoryStorage {
+    async fn get_client(&self, client_id: &str) -> Result<Option<OAuth2Client>> {
+        Ok(self.state().clients.get(client_id).cloned())
+    }
+}
+
+impl PendingAuthorizationRepository for MemoryStorage {
+    async fn create_pending_authorization(
+        &self,
+        client: &OAuth2Client,
+        request: AuthorizationRequest,
+        browser_binding: &str,
+        lifetimes: &TokenLifetimes,
+    ) -> Result<OAuth2PendingAuthorization> {
+        let pending = client.build_pending_authorization(request, browser_binding, lifetimes);
+        self.state().pending_authorizations.insert(pending.id().clone(), pending.clone());
+
+        Ok(pending)
+    }
+
+    async fn authorize_pending_authorization(
+        &self,
+        pending: OAuth2PendingAuthorization,
+        user_id: &str,
+        auth_time: i64,
+        multi_factor: bool,
+    ) -> std::result::Result<OAuth2PendingAuthorization, OAuth2PendingAuthorizationSetEspoIdError> {
+        let pending = pending.into_authorized(user_id, auth_time, multi_factor)?;
+        self.state().pending_authorizations.insert(pending.id().clone(), pending.clone());
+
+        Ok(pending)
+    }
+}
+
+impl AuthorizationCodeRepository for MemoryStorage {
+    async fn create_authorization_code(
+        &self,
+        client: &OAuth2Client,
+        pending: OAuth2PendingAuthorization,
+        lifetimes: &TokenLifetimes,
+    ) -> std::result::Result<OAuth2AuthorizationCode, OAuth2AuthorizationCodeCreationError> {
+        let pending_id = pending.id().clone();
+        let code = client.build_authorization_code(pending, lifetimes)?;
+
+        let mut state = self.state();
+        state.pending_authorizations.remove(&pending_id);
+        state.authorization_codes.insert(code.code.clone(), code.clone());
+
+        Ok(code)
+    }
+
+    async fn get_authorization_code(&self, code: &str) -> Result<Option<OAuth2AuthorizationCode>> {
+        Ok(self.state().authorization_codes.get(code).cloned())
+    }
+
+    async fn revoke_authorization_code_tokens(&self, code: &OAuth2AuthorizationCode) -> Result<()> {
+        let mut state = self.state();
+        if let Some(access_token) = &code.access_token {
+            state.access_tokens.remove(access_token);
+        }
+
+        if let Some(refresh_token) = &code.refresh_token {
+            state
+                .access_tokens
+                .retain(|_, atoken| atoken.refresh_token.as_ref() != Some(refresh_token));
+            state.refresh_tokens.remove(refresh_token);
+        }
+
+        Ok(())
+    }
+}
+
+impl TokenRepository for MemoryStorage {
+    async fn create_token_pair(
+        &self,
+        client: &OAuth2Client,
+        code: OAuth2AuthorizationCode,
+        lifetimes: &TokenLifetimes,
+    ) -> Result<Option<(AccessToken, RefreshToken)>> {
+        let atoken = client.build_access_token(code.user_id.clone(), code.scopes.clone(), lifetimes);
+        let rtoken = client.build_refresh_token(&code, lifetimes);
+
+        let mut state = self.state();
+        let stored = match state.authorization_codes.get_mut(&code.code) {
+            Some(stored) if !stored.is_consumed() => stored,
+            _ => return Ok(None),
+        };
+
+        stored.consumed_at = Some(atoken.issued_at);
+        stored.access_token = Some(atoken.token.clone());
+        stored.refresh_token = Some(rtoken.token.clone());
+
+        state.access_tokens.insert(atoken.token.clone(), atoken.clone());
+        state.refresh_tokens.insert(rtoken.token.clone(), rtoken.clone());
+
+        Ok(Some((atoken, rtoken)))
+    }
+
+    async fn refresh_access_token(
+        &self,
+        client: &OAuth2Client,
+        refresh_token: &RefreshToken,
+        lifetimes: &TokenLifetimes,
+    ) -> Result<AccessToken> {
+        let mut atoken = client.build_access_token(refresh_token.user_id.clone(), refresh_token.scopes.clone(), lifetimes);
+        atoken.refresh_token = Some(refresh_token.token.clone());
+        self.state().access_tokens.insert(atoken.token.clone(), atoken.clone());
+
+        Ok(atoken)
+    }
+
+    async fn get_access_token(&self, token: &str) -> Result<Option<AccessToken>> {
+        Ok(self.state().access_tokens.get(token).cloned())
+    }
+
+    async fn get_refresh_token(&self, token: &str) -> Result<Option<RefreshToken>> {
+        Ok(self.state().refresh_tokens.get(token).cloned())
+    }
+}
@@ -0,0 +1,93 @@
+//! Storage of the entities involved in the OAuth2 flow, abstracted over the backend.
+//! [Database](crate::driver::Database) stores them with sqlx,
+//! [MemoryStorage] keeps them in memory, for tests.
+//!
+//! Only the token endpoint is generic over [Storage], the traits cover just what it needs.
+//! Creating and authorizing pending authorizations is included to issue codes to it in tests.
+//! All other endpoints, including login and authorization, use [Database](crate::driver::Database) directly,
+//! as they also depend on sessions, consent, login throttling and second factors.
+
+use std::future::Future;
+
+use sqlx::Result;
+
+use crate::oauth2_client::{
+    AccessToken, AuthorizationRequest, OAuth2AuthorizationCode, OAuth2AuthorizationCodeCreationError, OAuth2Client,
+    OAuth2PendingAuthorization, OAuth2PendingAuthorizationSetEspoIdError, RefreshToken, TokenLifetimes,
+};
+
+mod memory;
+mod sql;
+
+pub use memory::MemoryStorage;
+
+pub trait ClientRepository {
+    fn get_client(&self, client_id: &str) -> impl Future<Output = Result<Option<OAuth2Client>>> + Send;
+}
+
+pub trait PendingAuthorizationRepository {
+    fn create_pending_authorization(
+        &self,
+        client: &OAuth2Client,
+        request: AuthorizationRequest,
+        browser_binding: &str,
+        lifetimes: &TokenLifetimes,
+    ) -> impl Future<Output = Result<OAuth2PendingAuthorization>> + Send;
+
+    /// Mark the authorization as authorized by the user, who last entered their credentials at `auth_time`
+    fn authorize_pending_authorization(
+        &self,
+        pending: OAuth2PendingAuthorization,
+        user_id: &str,
+        auth_time: i64,
+        multi_factor: bool,
+    ) -> impl Future<Output = std::result::Result<OAuth2PendingAuthorization, OAuth2PendingAuthorizationSetEspoIdError>> + Send;
+}
+
+pub trait AuthorizationCodeRepository {
+    /// Exchange an authorized pending authorization for an authorization code
+    fn create_authorization_code(
+        &self,
+        client: &OAuth2Client,
+        pending: OAuth2PendingAuthorization,
+        lifetimes: &TokenLifetimes,
+    ) -> impl Future<Output = std::result::Result<OAuth2AuthorizationCode, OAuth2AuthorizationCodeCreationError>> + Send;
+
+    fn get_authorization_code(&self, code: &str) -> impl Future<Output = Result<Option<OAuth2AuthorizationCode>>> + Send;
+
+    /// Revoke the tokens a consumed code was exchanged for, after it was replayed
+    fn revoke_authorization_code_tokens(&self, code: &OAuth2AuthorizationCode) -> impl Future<Output = Result<()>> + Send;
+}
+
+pub trait TokenRepository {
+    /// Exchange an authorization code for an access and refresh token.
+    /// Returns `None` if the code has already been consumed.
+    fn create_token_pair(
+        &self,
+        client: &OAuth2Client,
+        code: OAuth2AuthorizationCode,
+        lifetimes: &TokenLifetimes,
+    ) -> impl Future<Output = Result<Option<(AccessToken, RefreshToken)>>> + Send;
+
+    fn refresh_access_token(
+        &self,
+        client: &OAuth2Client,
+        refresh_token: &RefreshToken,
+        lifetimes: &TokenLifetimes,
+    ) -> impl Future<Output = Result<AccessToken>> + Send;
+
+    fn get_access_token(&self, token: &str) -> impl Future<Output = Result<Option<AccessToken>>> + Send;
+
+    fn get_refresh_token(&self, token: &str) -> impl Future<Output = Result<Option<RefreshToken>>> + Send;
+}
+
+/// All repositories, implemented by every storage backend
+pub trait Storage:
+    ClientRepository + PendingAuthorizationRepository + AuthorizationCodeRepository + TokenRepository + Send + Sync + 'static
+{
+}
+
+impl<T> Storage for T where
+    T: ClientRepository + PendingAuthorizationRepository + AuthorizationCodeRepository + TokenRepository + Send + Sync + 'static
+{
+}
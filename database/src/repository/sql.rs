@@ -0,0 +1,85 @@
+use sqlx::Result;
+
+use crate::driver::Database;
+use crate::oauth2_client::{
+    AccessToken, AuthorizationRequest, OAuth2AuthorizationCode, OAuth2AuthorizationCodeCreationError, OAuth2Client,
+    OAuth2PendingAuthorization, OAuth2PendingAuthorizationSetEspoIdError, RefreshToken, TokenLifetimes,
+};
+use crate::repository::{
+    AuthorizationCodeRepository, ClientRepository, PendingAuthorizationRepository, TokenRepository,
+};
+
+impl ClientRepository for Database {
+    async fn get_client(&self, client_id: &str) -> Result<Option<OAuth2Client>> {
+        OAuth2Client::get_by_client_id(self, client_id).await
+    }
+}
+
+impl PendingAuthorizationRepository for Database {
+    async fn create_pending_authorization(
+        &self,
+        client: &OAuth2Client,
+        request: AuthorizationRequest,
+        browser_binding: &str,
+        lifetimes: &TokenLifetimes,
+    ) -> Result<OAuth2PendingAuthorization> {
+        client.new_pending_authorization(self, request, browser_binding, lifetimes).await
+    }
+
+    async fn authorize_pending_authorization(
+        &self,
+        pending: OAuth2PendingAuthorization,
+        user_id: &str,
+        auth_time: i64,
+        multi_factor: bool,
+    ) -> std::result::Result<OAuth2PendingAuthorization, OAuth2PendingAuthorizationSetEspoIdError> {
+        pending.set_user_id(self, user_id, auth_time, multi_factor).await
+    }
+}
+
+impl AuthorizationCodeRepository for Database {
+    async fn create_authorization_code(
+        &self,
+        client: &OAuth2Client,
+        pending: OAuth2PendingAuthorization,
+        lifetimes: &TokenLifetimes,
+    ) -> std::result::Result<OAuth2AuthorizationCode, OAuth2AuthorizationCodeCreationError> {
+        client.new_authorization_code(self, pending, lifetimes).await
+    }
+
+    async fn get_authorization_code(&self, code: &str) -> Result<Option<OAuth2AuthorizationCode>> {
+        OAuth2AuthorizationCode::get_by_code(self, code).await
+    }
+
+    async fn revoke_authorization_code_tokens(&self, code: &OAuth2AuthorizationCode) -> Result<()> {
+        code.revoke_issued_tokens(self).await
+    }
+}
+
+impl TokenRepository for Database {
+    async fn create_token_pair(
+        &self,
+        client: &OAuth2Client,
+        code: OAuth2AuthorizationCode,
+        lifetimes: &TokenLifetimes,
+    ) -> Result<Option<(AccessToken, RefreshToken)>> {
+        client.new_token_pair(self, code, lifetimes).await
+    }
+
+    async fn refresh_access_token(
+        &self,
+        client: &OAuth2Client,
+        refresh_token: &RefreshToken,
+        lifetimes: &TokenLifetimes,
+    ) -> Result<AccessToken> {
+        client.refresh_access_token(self, refresh_token, lifetimes).await
+    }
+
+    async fn get_access_token(&self, token: &str) -> Result<Option<AccessToken>> {
+        AccessToken::get_by_token(self, token).await
+    }
+
+    async fn get_refresh_token(&self, token: &str) -> Result<Option<RefreshToken>> {
+        RefreshToken::get_by_token(self, token).await
+    }
+}
@@ -0,0 +1,101 @@
+use serde::Serialize;
+use sqlx::{FromRow, Result};
+
+use crate::driver::Database;
+use crate::with_pool;
+
+/// A registered scope. Clients can only request registered scopes.
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct Scope {
+    pub name: String,
+    /// Shown to the user when they are asked to authorize a client
+    pub description: String,
+    /// Whether all users are permitted this scope,
+    /// without it being granted to them or one of their groups
+    pub is_default: bool,
+}
+
+impl Scope {
+    pub async fn new(driver: &Database, name: String, description: String, is_default: bool) -> Result<Self> {
+        with_pool!(driver, |pool, sql| {
+            sqlx::query(&sql("INSERT INTO scopes (name, description, is_default) VALUES (?, ?, ?)"))
+                .bind(&name)
+                .bind(&description)
+                .bind(is_default)
+                .execute(pool)
+                .await
+                .map(|result| result.rows_affected())
+        })?;
+
+        Ok(Self {
+            name,
+            description,
+            is_default,
+        })
+    }
+
+    pub async fn list(driver: &Database) -> Result<Vec<Self>> {
+        with_pool!(driver, |pool, sql| {
+            sqlx::query_as(&sql("SELECT * FROM scopes"))
+                .fetch_all(pool)
+                .await
+        })
+    }
+
+    /// All scopes permitted to every user
+    pub async fn list_default(driver: &Database) -> Result<Vec<Self>> {
+        with_pool!(driver, |pool, sql| {
+            sqlx::query_as(&sql("SELECT * FROM scopes WHERE is_default = TRUE"))
+                .fetch_all(pool)
+                .await
+        })
+    }
+
+    pub async fn get_by_name(driver: &Database, name: &str) -> Result<Option<Self>> {
+        with_pool!(driver, |pool, sql| {
+            sqlx::query_as(&sql("SELECT * FROM scopes WHERE name = ?"))
+                .bind(name)
+                .fetch_optional(pool)
+                .await
+        })
+    }
+
+    pub async fn update(&mut self, driver: &Database, description: String, is_default: bool) -> Result<()> {
+        with_pool!(driver, |pool, sql| {
+            sqlx::query(&sql("UPDATE scopes SET description = ?, is_default = ? WHERE name = ?"))
+                .bind(&description)
+                .bind(is_default)
+                .bind(&self.name)
+                .execute(pool)
+                .await
+                .map(|result| result.rows_affected())
+        })?;
+
+        self.description = description;
+        self.is_default = is_default;
+        Ok(())
+    }
+
+    /// Delete the scope. It is removed from all clients, users and groups it was granted to.
+    /// Tokens already issued with the scope are left as-is.
+    pub async fn delete(self, driver: &Database) -> Result<()> {
+        with_pool!(driver, |pool, sql| {
+            let mut tx = pool.begin().await?;
+
+            for query in [
+                "DELETE FROM oauth2_client_scopes WHERE scope = ?",
+                "DELETE FROM user_permitted_scopes WHERE scope = ?",
+                "DELETE FROM user_group_permitted_scopes WHERE scope = ?",
+                "DELETE FROM scopes WHERE name = ?",
+            ] {
+                sqlx::query(&sql(query))
+                    .bind(&self.name)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+
+            tx.commit().await?;
+        });
+        Ok(())
+    }
+}
@@ -0,0 +1,95 @@
+use sqlx::{FromRow, Result};
+use time::OffsetDateTime;
+
+use crate::driver::Database;
+use crate::{generate_string, hash_secret};
+use crate::with_pool;
+
+/// A single sign-on session. It lets a user authorize further clients
+/// without logging in again. Only a hash of the session token is stored.
+#[derive(Debug, Clone, FromRow)]
+pub struct Session {
+    pub id: String,
+    pub user_id: String,
+    /// When the user last entered their credentials
+    pub authenticated_at: i64,
+    pub expires_at: i64,
+    /// Whether the user completed a second factor, or used a passkey
+    pub multi_factor: bool,
+}
+
+impl Session {
+    /// Start a new session for the user, who has just logged in.
+    /// Returns the session token alongside the session, it can not be retrieved later.
+    pub async fn new(driver: &Database, user_id: &str, lifetime: i64, multi_factor: bool) -> Result<(Self, String)> {
+        let id = generate_string(32);
+        let token = generate_string(48);
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+
+        let session = Self {
+            id,
+            user_id: user_id.to_string(),
+            authenticated_at: now,
+            expires_at: now + lifetime,
+            multi_factor,
+        };
+
+        with_pool!(driver, |pool, sql| {
+            sqlx::query(&sql("INSERT INTO sso_sessions (id, token_hash, user_id, authenticated_at, expires_at, multi_factor) VALUES (?, ?, ?, ?, ?, ?)"))
+                .bind(&session.id)
+                .bind(hash_secret(&token))
+                .bind(&session.user_id)
+                .bind(session.authenticated_at)
+                .bind(session.expires_at)
+                .bind(session.multi_factor)
+                .execute(pool)
+                .await
+                .map(|result| result.rows_affected())
+        })?;
+
+        Ok((session, token))
+    }
+
+    /// Look up a session, only returning it if it has not expired
+    pub async fn get_valid_by_token(driver: &Database, token: &str) -> Result<Option<Self>> {
+        let session: Option<Self> = with_pool!(driver, |pool, sql| {
+            sqlx::query_as(&sql("SELECT * FROM sso_sessions WHERE token_hash = ?"))
+                .bind(hash_secret(token))
+                .fetch_optional(pool)
+                .await
+        })?;
+
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+        Ok(session.filter(|session| now < session.expires_at))
+    }
+
+    /// Seconds since the user last entered their credentials
+    pub fn authentication_age(&self) -> i64 {
+        OffsetDateTime::now_utc().unix_timestamp() - self.authenticated_at
+    }
+
+    /// End all sessions of the user
+    pub async fn delete_by_user_id(driver: &Database, user_id: &str) -> Result<()> {
+        with_pool!(driver, |pool, sql| {
+            sqlx::query(&sql("DELETE FROM sso_sessions WHERE user_id = ?"))
+                .bind(user_id)
+                .execute(pool)
+                .await
+                .map(|result| result.rows_affected())
+        })?;
+
+        Ok(())
+    }
+
+    pub async fn delete(self, driver: &Database) -> Result<()> {
+        with_pool!(driver, |pool, sql| {
+            sqlx::query(&sql("DELETE FROM sso_sessions WHERE id = ?"))
+                .bind(&self.id)
+                .execute(pool)
+                .await
+                .map(|result| result.rows_affected())
+        })?;
+
+        Ok(())
+    }
+}
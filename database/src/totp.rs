@@ -0,0 +1,222 @@
+use rand::RngCore;
+use sqlx::{FromRow, Result};
+use thiserror::Error;
+use time::OffsetDateTime;
+use totp_rs::{Algorithm, Secret, TOTP};
+
+use crate::driver::Database;
+use crate::{generate_string, hash_secret};
+use crate::with_pool;
+
+/// Length of the TOTP secret in bytes. RFC 4226 recommends 160 bits.
+const SECRET_LENGTH: usize = 20;
+const DIGITS: usize = 6;
+const STEP: u64 = 30;
+/// Number of time steps before and after the current one that are still accepted.
+const SKEW: i64 = 1;
+const RECOVERY_CODE_COUNT: usize = 10;
+const RECOVERY_CODE_LENGTH: usize = 12;
+
+#[derive(Debug, Clone, FromRow)]
+pub struct UserTotp {
+    pub user_id: String,
+    secret: String,
+    pub confirmed: bool,
+    last_used_step: i64,
+}
+
+#[derive(Debug, Error)]
+pub enum TotpError {
+    #[error("{0}")]
+    Database(#[from] sqlx::Error),
+    #[error("Invalid TOTP configuration: {0}")]
+    Totp(#[from] totp_rs::TotpUrlError),
+    #[error("Invalid TOTP secret: {0}")]
+    Secret(#[from] totp_rs::SecretParseError),
+}
+
+impl UserTotp {
+    /// Start a new enrollment for the user.
+    /// Any existing, unconfirmed enrollment is replaced.
+    pub async fn enroll(driver: &Database, user_id: &str) -> Result<Self> {
+        let mut secret = vec![0_u8; SECRET_LENGTH];
+        rand::thread_rng().fill_bytes(&mut secret);
+        let secret = Secret::Raw(secret).to_encoded().to_string();
+
+        with_pool!(driver, |pool, sql| {
+            let mut tx = pool.begin().await?;
+
+            sqlx::query(&sql("DELETE FROM user_totp WHERE user_id = ? AND confirmed = FALSE"))
+                .bind(user_id)
+                .execute(&mut *tx)
+                .await?;
+
+            sqlx::query(&sql("INSERT INTO user_totp (user_id, secret, confirmed, last_used_step) VALUES (?, ?, FALSE, 0)"))
+                .bind(user_id)
+                .bind(&secret)
+                .execute(&mut *tx)
+                .await?;
+
+            tx.commit().await?;
+        });
+
+        Ok(Self {
+            user_id: user_id.to_string(),
+            secret,
+            confirmed: false,
+            last_used_step: 0,
+        })
+    }
+
+    pub async fn get_by_user_id(driver: &Database, user_id: &str) -> Result<Option<Self>> {
+        with_pool!(driver, |pool, sql| {
+            sqlx::query_as(&sql("SELECT * FROM user_totp WHERE user_id = ?"))
+                .bind(user_id)
+                .fetch_optional(pool)
+                .await
+        })
+    }
+
+    /// Get the TOTP configuration only if the user has completed enrollment
+    pub async fn get_confirmed(driver: &Database, user_id: &str) -> Result<Option<Self>> {
+        Ok(Self::get_by_user_id(driver, user_id)
+            .await?
+            .filter(|totp| totp.confirmed))
+    }
+
+    /// The base32 encoded secret, for users who cannot scan the provisioning URI
+    pub fn secret(&self) -> &str {
+        &self.secret
+    }
+
+    /// The `otpauth://` URI to be shown to the user, usually as a QR code
+    ///
+    /// # Errors
+    ///
+    /// If the issuer or account name contain a `:`
+    pub fn provisioning_uri(&self, issuer: &str, account_name: &str) -> std::result::Result<String, TotpError> {
+        Ok(self.totp(Some(issuer.to_string()), account_name.to_string())?.get_url())
+    }
+
+    /// Verify a code against the secret. A code can only be used once,
+    /// after a successful verification, codes of the same or earlier time steps are rejected.
+    pub async fn verify(&mut self, driver: &Database, code: &str) -> std::result::Result<bool, TotpError> {
+        let totp = self.totp(None, String::new())?;
+        let current_step = OffsetDateTime::now_utc().unix_timestamp() / STEP as i64;
+
+        let matched_step = (current_step - SKEW..=current_step + SKEW)
+            .filter(|step| *step > self.last_used_step)
+            .find(|step| totp.check(code, *step as u64 * STEP));
+
+        let step = match matched_step {
+            Some(step) => step,
+            None => return Ok(false),
+        };
+
+        // Concurrent verifications of the same code race here, only one of them may claim the step
+        let claimed = with_pool!(driver, |pool, sql| {
+            sqlx::query(&sql("UPDATE user_totp SET last_used_step = ? WHERE user_id = ? AND last_used_step < ?"))
+                .bind(step)
+                .bind(&self.user_id)
+                .bind(step)
+                .execute(pool)
+                .await
+                .map(|result| result.rows_affected())
+        })?;
+
+        if claimed == 0 {
+            return Ok(false);
+        }
+
+        self.last_used_step = step;
+        Ok(true)
+    }
+
+    /// Complete the enrollment. Returns a fresh set of recovery codes,
+    /// replacing any the user had before.
+    pub async fn confirm(&mut self, driver: &Database) -> Result<Vec<String>> {
+        with_pool!(driver, |pool, sql| {
+            sqlx::query(&sql("UPDATE user_totp SET confirmed = TRUE WHERE user_id = ?"))
+                .bind(&self.user_id)
+                .execute(pool)
+                .await
+                .map(|result| result.rows_affected())
+        })?;
+        self.confirmed = true;
+
+        RecoveryCode::regenerate(driver, &self.user_id).await
+    }
+
+    /// Remove TOTP and all recovery codes for the user
+    pub async fn delete(self, driver: &Database) -> Result<()> {
+        with_pool!(driver, |pool, sql| {
+            let mut tx = pool.begin().await?;
+
+            sqlx::query(&sql("DELETE FROM user_totp WHERE user_id = ?"))
+                .bind(&self.user_id)
+                .execute(&mut *tx)
+                .await?;
+
+            sqlx::query(&sql("DELETE FROM user_recovery_codes WHERE user_id = ?"))
+                .bind(&self.user_id)
+                .execute(&mut *tx)
+                .await?;
+
+            tx.commit().await?;
+        });
+        Ok(())
+    }
+
+    fn totp(&self, issuer: Option<String>, account_name: String) -> std::result::Result<TOTP, TotpError> {
+        let secret = Secret::Encoded(self.secret.clone()).to_bytes()?;
+        // Skew is handled by `verify`, as we need to know which step matched
+        Ok(TOTP::new(Algorithm::SHA1, DIGITS, 0, STEP, secret, issuer, account_name)?)
+    }
+}
+
+pub struct RecoveryCode;
+
+impl RecoveryCode {
+    /// Replace all recovery codes of the user with new ones.
+    /// The plaintext codes are returned, only their hashes are stored.
+    pub async fn regenerate(driver: &Database, user_id: &str) -> Result<Vec<String>> {
+        let codes = (0..RECOVERY_CODE_COUNT)
+            .map(|_| generate_string(RECOVERY_CODE_LENGTH))
+            .collect::<Vec<_>>();
+
+        with_pool!(driver, |pool, sql| {
+            let mut tx = pool.begin().await?;
+
+            sqlx::query(&sql("DELETE FROM user_recovery_codes WHERE user_id = ?"))
+                .bind(user_id)
+                .execute(&mut *tx)
+                .await?;
+
+            for code in &codes {
+                sqlx::query(&sql("INSERT INTO user_recovery_codes (user_id, code) VALUES (?, ?)"))
+                    .bind(user_id)
+                    .bind(hash_secret(code))
+                    .execute(&mut *tx)
+                    .await?;
+            }
+
+            tx.commit().await?;
+        });
+        Ok(codes)
+    }
+
+    /// Use a recovery code. Returns whether the code was valid.
+    /// A code can only be used once.
+    pub async fn consume(driver: &Database, user_id: &str, code: &str) -> Result<bool> {
+        let deleted = with_pool!(driver, |pool, sql| {
+            sqlx::query(&sql("DELETE FROM user_recovery_codes WHERE user_id = ? AND code = ?"))
+                .bind(user_id)
+                .bind(hash_secret(code.trim()))
+                .execute(pool)
+                .await
+                .map(|result| result.rows_affected())
+        })?;
+
+        Ok(deleted > 0)
+    }
+}
@@ -1,10 +1,14 @@
+use std::sync::OnceLock;
+
 use serde::Serialize;
 use sqlx::{FromRow, Result};
 use thiserror::Error;
 
 use crate::driver::Database;
 use crate::generate_string;
-use crate::hash::{hash, verify};
+use crate::hash::{hash, needs_rehash, verify, HashError, PasswordHashing};
+use crate::password_policy::{PasswordPolicy, PasswordPolicyViolation};
+use crate::with_pool;
 
 #[derive(Debug, Clone, FromRow, Serialize)]
 pub struct User {
@@ -19,7 +23,9 @@ pub enum HashingError {
     #[error("{0}")]
     Database(#[from] sqlx::Error),
     #[error("{0}")]
-    BCrypt(#[from] bcrypt::BcryptError),
+    Hash(#[from] HashError),
+    #[error("{0}")]
+    Policy(#[from] PasswordPolicyViolation),
 }
 
 impl User {
@@ -31,13 +37,16 @@ impl User {
     ) -> Result<Self> {
         let user_id = generate_string(32);
 
-        sqlx::query("INSERT INTO users (user_id, name, email, is_admin) VALUES (?, ?, ?, ?)")
-            .bind(&user_id)
-            .bind(&name)
-            .bind(&email)
-            .bind(is_admin)
-            .execute(&**driver)
-            .await?;
+        with_pool!(driver, |pool, sql| {
+            sqlx::query(&sql("INSERT INTO users (user_id, name, email, is_admin) VALUES (?, ?, ?, ?)"))
+                .bind(&user_id)
+                .bind(&name)
+                .bind(&email)
+                .bind(is_admin)
+                .execute(pool)
+                .await
+                .map(|result| result.rows_affected())
+        })?;
 
         Ok(Self {
             name,
@@ -47,96 +56,178 @@ impl User {
         })
     }
 
-    pub async fn set_password(&self, password: &str, pepper: &str, driver: &Database) -> std::result::Result<(), HashingError> {
-        let salt = generate_string(16);
-        let password = hash(&password, &salt, pepper)?;
+    /// Set a new password for the user. The password must satisfy the `policy`.
+    pub async fn set_password(&self, password: &str, policy: &PasswordPolicy, hashing: &PasswordHashing, driver: &Database) -> std::result::Result<(), HashingError> {
+        policy.check(password, &self.email)?;
+        self.store_password(password, hashing, driver).await
+    }
+
+    async fn store_password(&self, password: &str, hashing: &PasswordHashing, driver: &Database) -> std::result::Result<(), HashingError> {
+        let pepper = hashing.current_pepper().ok_or(HashError::NoPepper)?;
+        // Hashing is deliberately slow, keep it off the async executor
+        let password = {
+            let (password, pepper, params) = (password.to_string(), pepper.pepper.clone(), hashing.params.clone());
+            tokio::task::spawn_blocking(move || hash(&password, &pepper, &params)).await.map_err(HashError::from)??
+        };
 
         if self.has_password(driver).await? {
-            sqlx::query("UPDATE user_credentials SET password = ?, salt = ? WHERE user_id = ?")
-                .bind(password)
-                .bind(salt)
-                .bind(&self.user_id)
-                .execute(&**driver)
-                .await?;
+            with_pool!(driver, |pool, sql| {
+                sqlx::query(&sql("UPDATE user_credentials SET password = ?, pepper_version = ? WHERE user_id = ?"))
+                    .bind(password)
+                    .bind(pepper.version)
+                    .bind(&self.user_id)
+                    .execute(pool)
+                    .await
+                    .map(|result| result.rows_affected())
+            })?;
         } else {
-            sqlx::query("INSERT INTO user_credentials (user_id, password, salt) VALUES (?, ?, ?)")
-                .bind(&self.user_id)
-                .bind(password)
-                .bind(salt)
-                .execute(&**driver)
-                .await?;
+            with_pool!(driver, |pool, sql| {
+                sqlx::query(&sql("INSERT INTO user_credentials (user_id, password, pepper_version) VALUES (?, ?, ?)"))
+                    .bind(&self.user_id)
+                    .bind(password)
+                    .bind(pepper.version)
+                    .execute(pool)
+                    .await
+                    .map(|result| result.rows_affected())
+            })?;
         }
 
         Ok(())
     }
 
     async fn has_password(&self, driver: &Database) -> Result<bool> {
-        Ok(
-            sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM user_credentials WHERE user_id = ? LIMIT 1)")
+        let user_id: Option<String> = with_pool!(driver, |pool, sql| {
+            sqlx::query_scalar(&sql("SELECT user_id FROM user_credentials WHERE user_id = ?"))
                 .bind(&self.user_id)
-                .fetch_one(&**driver)
-                .await?
-        )
+                .fetch_optional(pool)
+                .await
+        })?;
+
+        Ok(user_id.is_some())
     }
 
-    pub async fn verify_password(&self, password: &str, pepper: &str, driver: &Database) -> std::result::Result<bool, HashingError> {
-        if !self.has_password(&driver).await? {
+    /// Verify the password of the user.
+    /// If the stored hash does not use the current hashing scheme or pepper,
+    /// it is replaced with a new hash after successful verification.
+    ///
+    /// Hashes created with a pepper that is no longer configured never verify.
+    pub async fn verify_password(&self, password: &str, hashing: &PasswordHashing, driver: &Database) -> std::result::Result<bool, HashingError> {
+        if !self.has_password(driver).await? {
+            Self::verify_dummy_password(password, hashing).await?;
+            return Ok(false)
+        }
+
+        let (stored_password, pepper_version): (String, i32) = with_pool!(driver, |pool, sql| {
+            sqlx::query_as(&sql("SELECT password, pepper_version FROM user_credentials WHERE user_id = ?"))
+                .bind(&self.user_id)
+                .fetch_one(pool)
+                .await
+        })?;
+
+        let pepper = match hashing.pepper(pepper_version) {
+            Some(pepper) => pepper,
+            None => {
+                Self::verify_dummy_password(password, hashing).await?;
+                return Ok(false)
+            }
+        };
+
+        let valid = {
+            let (stored_password, password, pepper) = (stored_password.clone(), password.to_string(), pepper.pepper.clone());
+            tokio::task::spawn_blocking(move || verify(&stored_password, &password, &pepper)).await.map_err(HashError::from)??
+        };
+        if !valid {
             return Ok(false)
         }
 
-        let stored_password: String = sqlx::query_scalar("SELECT password FROM user_credentials WHERE user_id = ?")
-            .bind(&self.user_id)
-            .fetch_one(&**driver)
-            .await?;
+        let current_version = hashing.current_pepper().map(|pepper| pepper.version);
+        if needs_rehash(&stored_password, &hashing.params) || current_version != Some(pepper_version) {
+            // The password may predate the current policy, it should not be rejected here
+            self.store_password(password, hashing, driver).await?;
+        }
+
+        Ok(true)
+    }
 
-        Ok(verify(&stored_password, password, pepper)?)
+    /// Spend the same effort as [verify_password](Self::verify_password) without a user,
+    /// so that unknown accounts can not be told apart by response time. Never verifies.
+    pub async fn verify_dummy_password(password: &str, hashing: &PasswordHashing) -> std::result::Result<(), HashingError> {
+        static DUMMY_HASH: OnceLock<String> = OnceLock::new();
+
+        let pepper = hashing.current_pepper().ok_or(HashError::NoPepper)?.pepper.clone();
+        let (password, params) = (password.to_string(), hashing.params.clone());
+        tokio::task::spawn_blocking(move || {
+            let stored_password = match DUMMY_HASH.get() {
+                Some(stored_password) => stored_password,
+                None => {
+                    let stored_password = hash(&generate_string(32), &pepper, &params)?;
+                    DUMMY_HASH.get_or_init(|| stored_password)
+                }
+            };
+
+            verify(stored_password, &password, &pepper).map(|_| ())
+        }).await.map_err(HashError::from)??;
+
+        Ok(())
     }
 
     pub async fn get_by_id(driver: &Database, id: &str) -> Result<Option<Self>> {
-        Ok(sqlx::query_as("SELECT * FROM users WHERE user_id = ?")
-            .bind(id)
-            .fetch_optional(&**driver)
-            .await?)
+        with_pool!(driver, |pool, sql| {
+            sqlx::query_as(&sql("SELECT * FROM users WHERE user_id = ?"))
+                .bind(id)
+                .fetch_optional(pool)
+                .await
+        })
     }
 
     pub async fn get_by_email(driver: &Database, email: &str) -> Result<Option<Self>> {
-        Ok(sqlx::query_as("SELECT * FROM users WHERE email = ?")
-            .bind(email)
-            .fetch_optional(&**driver)
-            .await?)
+        with_pool!(driver, |pool, sql| {
+            sqlx::query_as(&sql("SELECT * FROM users WHERE email = ?"))
+                .bind(email)
+                .fetch_optional(pool)
+                .await
+        })
     }
 
     pub async fn list(driver: &Database) -> Result<Vec<Self>> {
-        Ok(sqlx::query_as("SELECT * FROM users")
-            .fetch_all(&**driver)
-            .await?)
+        with_pool!(driver, |pool, sql| {
+            sqlx::query_as(&sql("SELECT * FROM users"))
+                .fetch_all(pool)
+                .await
+        })
     }
 
     pub async fn list_permitted_scopes(&self, driver: &Database) -> Result<Vec<String>> {
-        Ok(
-            sqlx::query_scalar("SELECT scope FROM user_permitted_scopes WHERE user_id = ?")
+        with_pool!(driver, |pool, sql| {
+            sqlx::query_scalar(&sql("SELECT scope FROM user_permitted_scopes WHERE user_id = ?"))
                 .bind(&self.user_id)
-                .fetch_all(&**driver)
-                .await?,
-        )
+                .fetch_all(pool)
+                .await
+        })
     }
 
     pub async fn remove_permitted_scope(&self, driver: &Database, scope: &str) -> Result<()> {
-        sqlx::query("DELETE FROM user_permitted_scopes WHERE user_id = ? AND scope = ?")
-            .bind(&self.user_id)
-            .bind(scope)
-            .execute(&**driver)
-            .await?;
+        with_pool!(driver, |pool, sql| {
+            sqlx::query(&sql("DELETE FROM user_permitted_scopes WHERE user_id = ? AND scope = ?"))
+                .bind(&self.user_id)
+                .bind(scope)
+                .execute(pool)
+                .await
+                .map(|result| result.rows_affected())
+        })?;
 
         Ok(())
     }
 
     pub async fn grant_permitted_scope(&self, driver: &Database, scope: &str) -> Result<()> {
-        sqlx::query("INSERT INTO user_permitted_scopes (user_id, scope) VALUES (?, ?)")
-            .bind(&self.user_id)
-            .bind(scope)
-            .execute(&**driver)
-            .await?;
+        with_pool!(driver, |pool, sql| {
+            sqlx::query(&sql("INSERT INTO user_permitted_scopes (user_id, scope) VALUES (?, ?)"))
+                .bind(&self.user_id)
+                .bind(scope)
+                .execute(pool)
+                .await
+                .map(|result| result.rows_affected())
+        })?;
 
         Ok(())
     }
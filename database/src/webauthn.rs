@@ -0,0 +1,225 @@
+use base64::Engine;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use sha2::Digest;
+use sqlx::{FromRow, Result};
+use thiserror::Error;
+use time::{Duration, OffsetDateTime};
+use webauthn_rs::prelude::{AuthenticationResult, Passkey, Uuid};
+
+use crate::driver::Database;
+use crate::generate_string;
+use crate::with_pool;
+
+#[derive(Debug, Clone, FromRow)]
+pub struct WebauthnCredential {
+    /// Base64url encoded credential ID, as assigned by the authenticator
+    pub credential_id: String,
+    pub user_id: String,
+    pub name: String,
+    passkey: String,
+    pub created_at: i64,
+    pub last_used_at: Option<i64>,
+}
+
+/// Server side state of an in-progress registration or authentication ceremony
+pub struct WebauthnChallenge;
+
+#[derive(Debug, Error)]
+pub enum WebauthnStorageError {
+    #[error("{0}")]
+    Database(#[from] sqlx::Error),
+    #[error("Failed to (de)serialize WebAuthn state: {0}")]
+    Serialization(#[from] serde_json::Error),
+}
+
+/// The WebAuthn user handle for a user.
+/// Derived from the user ID, so it does not need to be stored.
+pub fn user_handle(user_id: &str) -> Uuid {
+    let digest = sha2::Sha256::digest(user_id);
+    let mut bytes = [0_u8; 16];
+    bytes.copy_from_slice(&digest[..16]);
+
+    Uuid::from_bytes(bytes)
+}
+
+/// Encode a raw credential ID the way it is stored
+pub fn encode_credential_id(credential_id: &[u8]) -> String {
+    base64::prelude::BASE64_URL_SAFE_NO_PAD.encode(credential_id)
+}
+
+impl WebauthnCredential {
+    pub async fn new(
+        driver: &Database,
+        user_id: &str,
+        name: String,
+        passkey: &Passkey,
+    ) -> std::result::Result<Self, WebauthnStorageError> {
+        let credential_id = encode_credential_id(passkey.cred_id());
+        let serialized = serde_json::to_string(passkey)?;
+        let created_at = OffsetDateTime::now_utc().unix_timestamp();
+
+        with_pool!(driver, |pool, sql| {
+            sqlx::query(&sql("INSERT INTO user_webauthn_credentials (credential_id, user_id, name, passkey, created_at) VALUES (?, ?, ?, ?, ?)"))
+                .bind(&credential_id)
+                .bind(user_id)
+                .bind(&name)
+                .bind(&serialized)
+                .bind(created_at)
+                .execute(pool)
+                .await
+                .map(|result| result.rows_affected())
+        })?;
+
+        Ok(Self {
+            credential_id,
+            user_id: user_id.to_string(),
+            name,
+            passkey: serialized,
+            created_at,
+            last_used_at: None,
+        })
+    }
+
+    pub async fn list_by_user_id(driver: &Database, user_id: &str) -> Result<Vec<Self>> {
+        with_pool!(driver, |pool, sql| {
+            sqlx::query_as(&sql("SELECT * FROM user_webauthn_credentials WHERE user_id = ?"))
+                .bind(user_id)
+                .fetch_all(pool)
+                .await
+        })
+    }
+
+    pub async fn get_by_credential_id(driver: &Database, credential_id: &str) -> Result<Option<Self>> {
+        with_pool!(driver, |pool, sql| {
+            sqlx::query_as(&sql("SELECT * FROM user_webauthn_credentials WHERE credential_id = ?"))
+                .bind(credential_id)
+                .fetch_optional(pool)
+                .await
+        })
+    }
+
+    pub fn passkey(&self) -> std::result::Result<Passkey, WebauthnStorageError> {
+        Ok(serde_json::from_str(&self.passkey)?)
+    }
+
+    pub async fn rename(&mut self, driver: &Database, name: String) -> Result<()> {
+        with_pool!(driver, |pool, sql| {
+            sqlx::query(&sql("UPDATE user_webauthn_credentials SET name = ? WHERE credential_id = ?"))
+                .bind(&name)
+                .bind(&self.credential_id)
+                .execute(pool)
+                .await
+                .map(|result| result.rows_affected())
+        })?;
+
+        self.name = name;
+        Ok(())
+    }
+
+    /// Record a successful authentication with this credential.
+    /// Updates the signature counter and backup state stored in the passkey.
+    pub async fn record_use(
+        &mut self,
+        driver: &Database,
+        result: &AuthenticationResult,
+    ) -> std::result::Result<(), WebauthnStorageError> {
+        let mut passkey = self.passkey()?;
+        passkey.update_credential(result);
+        let serialized = serde_json::to_string(&passkey)?;
+        let last_used_at = OffsetDateTime::now_utc().unix_timestamp();
+
+        with_pool!(driver, |pool, sql| {
+            sqlx::query(&sql("UPDATE user_webauthn_credentials SET passkey = ?, last_used_at = ? WHERE credential_id = ?"))
+                .bind(&serialized)
+                .bind(last_used_at)
+                .bind(&self.credential_id)
+                .execute(pool)
+                .await
+                .map(|result| result.rows_affected())
+        })?;
+
+        self.passkey = serialized;
+        self.last_used_at = Some(last_used_at);
+        Ok(())
+    }
+
+    pub async fn delete(self, driver: &Database) -> Result<()> {
+        with_pool!(driver, |pool, sql| {
+            sqlx::query(&sql("DELETE FROM user_webauthn_credentials WHERE credential_id = ?"))
+                .bind(&self.credential_id)
+                .execute(pool)
+                .await
+                .map(|result| result.rows_affected())
+        })?;
+        Ok(())
+    }
+}
+
+impl WebauthnChallenge {
+    fn generate_id() -> String {
+        generate_string(32)
+    }
+
+    fn generate_expiry() -> i64 {
+        (OffsetDateTime::now_utc() + Duration::minutes(5)).unix_timestamp()
+    }
+
+    /// Store the state of a ceremony. The `subject` is what the ceremony is bound to,
+    /// e.g. the user registering a credential or the pending authorization being logged in to.
+    /// Returns the ID of the challenge.
+    pub async fn store<T: Serialize>(
+        driver: &Database,
+        subject: &str,
+        state: &T,
+    ) -> std::result::Result<String, WebauthnStorageError> {
+        let id = Self::generate_id();
+
+        with_pool!(driver, |pool, sql| {
+            sqlx::query(&sql("INSERT INTO webauthn_challenges (id, subject, state, expires_at) VALUES (?, ?, ?, ?)"))
+                .bind(&id)
+                .bind(subject)
+                .bind(serde_json::to_string(state)?)
+                .bind(Self::generate_expiry())
+                .execute(pool)
+                .await
+                .map(|result| result.rows_affected())
+        })?;
+
+        Ok(id)
+    }
+
+    /// Retrieve and remove the state of a ceremony.
+    /// Returns `None` if the challenge does not exist, has expired, or belongs to a different subject.
+    pub async fn take<T: DeserializeOwned>(
+        driver: &Database,
+        id: &str,
+        subject: &str,
+    ) -> std::result::Result<Option<T>, WebauthnStorageError> {
+        let row: Option<(String, i64)> = with_pool!(driver, |pool, sql| {
+            let mut tx = pool.begin().await?;
+
+            let row = sqlx::query_as(&sql("SELECT state, expires_at FROM webauthn_challenges WHERE id = ? AND subject = ?"))
+                .bind(id)
+                .bind(subject)
+                .fetch_optional(&mut *tx)
+                .await?;
+
+            sqlx::query(&sql("DELETE FROM webauthn_challenges WHERE id = ? AND subject = ?"))
+                .bind(id)
+                .bind(subject)
+                .execute(&mut *tx)
+                .await?;
+
+            tx.commit().await?;
+            row
+        });
+
+        let state = match row {
+            Some((state, expires_at)) if OffsetDateTime::now_utc().unix_timestamp() < expires_at => state,
+            _ => return Ok(None),
+        };
+
+        Ok(Some(serde_json::from_str(&state)?))
+    }
+}
@@ -1,7 +1,10 @@
 use crate::config::{get_config, DefaultClientConfig};
+use crate::migrate::prepare_schema;
+use crate::purge::spawn_purge_task;
 use actix_cors::Cors;
 use actix_route_config::Routable;
 use actix_web::{web, App, HttpServer};
+use color_eyre::eyre::bail;
 use color_eyre::Result;
 use database::driver::Database;
 use database::oauth2_client::OAuth2Client;
@@ -15,25 +18,36 @@ use tracing_subscriber::EnvFilter;
 
 mod routes;
 mod config;
+mod purge;
+mod migrate;
 
 #[tokio::main]
 async fn main() -> Result<()> {
     color_eyre::install()?;
     install_tracing();
 
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    match args.first().map(String::as_str) {
+        None => {}
+        Some("migrate") => return migrate::run(&args[1..]).await,
+        Some(other) => bail!("Unknown command `{other}`. Usage: miniboss [migrate <status|up> [--dry-run]]"),
+    }
+
     let config = get_config().await?;
-    let database = Database::new(
-        &config.database.user,
-        &config.database.password,
-        &config.database.host,
-        &config.database.database,
-    )
-        .await?;
+    let database = config.database.connect().await?;
+    prepare_schema(&database, &config).await?;
 
     ensure_internal_oauth_client_exists(&database, &config.default_client).await?;
+    spawn_purge_task(database.clone(), &config);
+
+    let password_policy = config.password_policy.load().await?;
+    if !password_policy.breached_passwords.is_empty() {
+        info!("Loaded {} breached passwords", password_policy.breached_passwords.len());
+    }
 
     let w_database = web::Data::new(database);
     let w_config = web::Data::new(config);
+    let w_password_policy = web::Data::new(password_policy);
 
     HttpServer::new(move || {
         App::new()
@@ -41,6 +55,7 @@ async fn main() -> Result<()> {
             .wrap(TracingLogger::<NoiselessRootSpanBuilder>::new())
             .app_data(w_database.clone())
             .app_data(w_config.clone())
+            .app_data(w_password_policy.clone())
             .configure(routes::Router::configure)
     })
         .bind("0.0.0.0:8080")?
@@ -64,6 +79,7 @@ async fn ensure_internal_oauth_client_exists(
         "Miniboss".to_string(),
         config.redirect_uri.clone(),
         true,
+        true,
     )
         .await?;
 
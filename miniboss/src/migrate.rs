@@ -0,0 +1,106 @@
+use crate::config::{get_config, Config};
+use color_eyre::eyre::bail;
+use color_eyre::Result;
+use database::driver::{Database, MigrationStatus};
+use database::oauth2_client::OAuth2PendingAuthorization;
+use tracing::info;
+
+const USAGE: &str = "Usage: miniboss migrate <status|up> [--dry-run]";
+
+/// `miniboss migrate`. Shows or applies migrations, without starting the server.
+pub async fn run(args: &[String]) -> Result<()> {
+    let args = args.iter().map(String::as_str).collect::<Vec<_>>();
+    let (command, dry_run) = match args.as_slice() {
+        [command] => (*command, false),
+        [command, "--dry-run"] => (*command, true),
+        _ => bail!(USAGE),
+    };
+
+    let config = get_config().await?;
+    let database = config.database.connect().await?;
+    let status = database.migration_status().await?;
+
+    match command {
+        "status" => print_status(&status),
+        "up" => {
+            check_status(&status)?;
+
+            let pending = status.pending().collect::<Vec<_>>();
+            if pending.is_empty() {
+                println!("No pending migrations");
+                return Ok(());
+            }
+
+            for migration in &pending {
+                println!("{} {}", migration.version, migration.description);
+            }
+
+            if dry_run {
+                println!("{} pending migration(s), none applied", pending.len());
+            } else {
+                apply_migrations(&database, &config).await?;
+                println!("Applied {} migration(s)", pending.len());
+            }
+        }
+        _ => bail!(USAGE),
+    }
+
+    Ok(())
+}
+
+/// Apply pending migrations, unless `skip_migrations` is set.
+/// Fails if the schema is newer than this build, or if it is out of date and migrations are skipped.
+pub async fn prepare_schema(database: &Database, config: &Config) -> Result<()> {
+    let status = database.migration_status().await?;
+    check_status(&status)?;
+
+    let pending = status.pending().count();
+    if pending == 0 {
+        return Ok(());
+    }
+
+    if config.database.skip_migrations {
+        bail!("{pending} migration(s) pending. Apply them with `miniboss migrate up`");
+    }
+
+    info!("Applying {pending} migration(s)");
+    apply_migrations(database, config).await
+}
+
+/// Apply pending migrations, then fill in the values which depend on the configuration
+async fn apply_migrations(database: &Database, config: &Config) -> Result<()> {
+    database.migrate().await?;
+    OAuth2PendingAuthorization::backfill_expiry(database, &config.token_lifetimes.lifetimes()).await?;
+
+    Ok(())
+}
+
+fn check_status(status: &MigrationStatus) -> Result<()> {
+    if let Some(version) = status.dirty {
+        bail!("Migration {version} failed part way. Repair the database by hand before continuing");
+    }
+
+    if status.is_schema_newer() {
+        bail!(
+            "The database schema is newer than this build, unknown migration(s): {:?}. Upgrade Miniboss",
+            status.unknown
+        );
+    }
+
+    Ok(())
+}
+
+fn print_status(status: &MigrationStatus) {
+    for migration in &status.migrations {
+        let state = if migration.applied { "applied" } else { "pending" };
+        println!("{:>4} {state:<8} {}", migration.version, migration.description);
+    }
+
+    for version in &status.unknown {
+        println!("{version:>4} unknown  (not part of this build)");
+    }
+
+    if let Some(version) = status.dirty {
+        println!("Migration {version} failed part way");
+    }
+}
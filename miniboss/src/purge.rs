@@ -0,0 +1,39 @@
+use std::time::Duration;
+
+use database::driver::Database;
+use database::purge::purge_expired;
+use tokio::time::MissedTickBehavior;
+use tracing::{debug, info, warn};
+
+use crate::config::Config;
+
+/// Periodically delete expired tokens, codes, pending authorizations, WebAuthn challenges and sessions,
+/// and login throttles which have run out
+pub fn spawn_purge_task(database: Database, config: &Config) {
+    let mut interval = tokio::time::interval(Duration::from_secs(config.purge.interval_seconds));
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    let batch_size = config.purge.batch_size;
+    let throttle_policy = config.login_throttle.policy();
+
+    tokio::spawn(async move {
+        loop {
+            interval.tick().await;
+
+            match purge_expired(&database, batch_size, &throttle_policy).await {
+                Ok(counts) if counts.total() == 0 => debug!("Nothing to purge"),
+                Ok(counts) => info!(
+                    access_tokens = counts.access_tokens,
+                    refresh_tokens = counts.refresh_tokens,
+                    authorization_codes = counts.authorization_codes,
+                    pending_authorizations = counts.pending_authorizations,
+                    webauthn_challenges = counts.webauthn_challenges,
+                    sessions = counts.sessions,
+                    login_throttles = counts.login_throttles,
+                    "Purged expired rows"
+                ),
+                Err(e) => warn!("Failed to purge expired rows: {e}"),
+            }
+        }
+    });
+}
@@ -1,6 +1,8 @@
 use actix_web::web;
 use database::driver::Database;
+use database::password_policy::PasswordPolicy;
 use crate::config::Config;
 
 pub type WDatabase = web::Data<Database>;
-pub type WConfig = web::Data<Config>;
\ No newline at end of file
+pub type WConfig = web::Data<Config>;
+pub type WPasswordPolicy = web::Data<PasswordPolicy>;
\ No newline at end of file
@@ -6,16 +6,31 @@ use actix_web::{FromRequest, HttpRequest};
 use actix_web::cookie::time::OffsetDateTime;
 use actix_web::dev::Payload;
 
-use database::oauth2_client::AccessToken;
+use database::oauth2_client::{AccessToken, OAuth2Client};
+use database::personal_access_token::{PersonalAccessToken, TOKEN_PREFIX};
 use database::user::User;
 
 use crate::routes::appdata::WDatabase;
 use crate::routes::error::{WebError, WebResult};
+use crate::routes::scopes::ADMIN_SCOPE;
 
 #[derive(Debug, Clone)]
 pub struct Auth {
     pub user: User,
-    token: AccessToken,
+    token: AuthToken,
+}
+
+/// The token the request was authenticated with
+#[derive(Debug, Clone)]
+enum AuthToken {
+    /// Issued through an OAuth2 flow.
+    /// First party tokens are issued to Miniboss' own, internal client, rather than a third party application.
+    OAuth2 {
+        token: AccessToken,
+        first_party: bool,
+    },
+    /// Created by the user
+    Personal(PersonalAccessToken),
 }
 
 impl FromRequest for Auth {
@@ -32,18 +47,41 @@ impl FromRequest for Auth {
         Box::pin(async move {
             let token = get_authorization_token(&req)?;
 
-            let token_info = match AccessToken::get_by_token(&database, &token).await? {
-                Some(v) => {
-                    if v.expires_at < OffsetDateTime::now_utc().unix_timestamp() {
-                        return Err(WebError::Unauthorized);
-                    } else {
-                        v
+            let token_info = if token.starts_with(TOKEN_PREFIX) {
+                let mut personal_token = PersonalAccessToken::get_valid_by_token(&database, &token)
+                    .await?
+                    .ok_or(WebError::Unauthorized)?;
+                personal_token.touch(&database).await?;
+
+                AuthToken::Personal(personal_token)
+            } else {
+                let token = match AccessToken::get_by_token(&database, &token).await? {
+                    Some(v) => {
+                        if v.expires_at < OffsetDateTime::now_utc().unix_timestamp() {
+                            return Err(WebError::Unauthorized);
+                        } else {
+                            v
+                        }
                     }
+                    None => return Err(WebError::Unauthorized),
+                };
+
+                let client = OAuth2Client::get_by_client_id(&database, &token.client_id)
+                    .await?
+                    .ok_or(WebError::Unauthorized)?;
+
+                AuthToken::OAuth2 {
+                    token,
+                    first_party: client.is_internal,
                 }
-                None => return Err(WebError::Unauthorized),
             };
 
-            let user = User::get_by_id(&database, &token_info.user_id)
+            let user_id = match &token_info {
+                AuthToken::OAuth2 { token, .. } => &token.user_id,
+                AuthToken::Personal(v) => &v.user_id,
+            };
+
+            let user = User::get_by_id(&database, user_id)
                 .await?
                 .ok_or(WebError::Unauthorized)?;
 
@@ -58,11 +96,47 @@ impl FromRequest for Auth {
 impl Auth {
     #[must_use]
     pub fn has_scope(&self, scope: &str) -> bool {
-        self.token.scopes().contains(scope)
+        self.scopes().contains(scope)
     }
 
     pub fn scopes(&self) -> HashSet<String> {
-        self.token.scopes()
+        match &self.token {
+            AuthToken::OAuth2 { token, .. } => token.scopes(),
+            AuthToken::Personal(v) => v.scopes(),
+        }
+    }
+
+    /// Require the request to come from Miniboss itself, with an OAuth2 token issued to the internal client.
+    /// Neither third party applications nor personal access tokens should be able to manage the user's credentials.
+    ///
+    /// # Errors
+    ///
+    /// [WebError::Forbidden] if a personal access token, or a token issued to a third party client was used
+    pub fn require_first_party(&self) -> WebResult<()> {
+        match &self.token {
+            AuthToken::OAuth2 { first_party: true, .. } => Ok(()),
+            _ => Err(WebError::Forbidden),
+        }
+    }
+
+    /// Require the user to be an administrator.
+    /// OAuth2 tokens must have been issued to the internal client,
+    /// personal access tokens must have been granted the [ADMIN_SCOPE].
+    ///
+    /// # Errors
+    ///
+    /// [WebError::Forbidden] if the user is not an administrator,
+    /// a token issued to a third party client, or a personal access token without the admin scope was used
+    pub fn require_admin(&self) -> WebResult<()> {
+        if !self.user.is_admin {
+            return Err(WebError::Forbidden);
+        }
+
+        match &self.token {
+            AuthToken::OAuth2 { first_party, .. } if *first_party => Ok(()),
+            AuthToken::Personal(_) if self.has_scope(ADMIN_SCOPE) => Ok(()),
+            _ => Err(WebError::Forbidden),
+        }
     }
 }
 
@@ -85,4 +159,85 @@ fn get_authorization_token(req: &HttpRequest) -> WebResult<String> {
     }
 
     Err(WebError::Unauthorized)
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use database::oauth2_client::AccessToken;
+    use database::personal_access_token::PersonalAccessToken;
+    use database::user::User;
+
+    use crate::routes::error::WebError;
+    use crate::routes::scopes::ADMIN_SCOPE;
+
+    use super::{Auth, AuthToken};
+
+    fn admin() -> User {
+        User {
+            user_id: "admin".to_string(),
+            name: "Admin".to_string(),
+            email: "admin@example.com".to_string(),
+            is_admin: true,
+        }
+    }
+
+    fn oauth2(first_party: bool) -> Auth {
+        Auth {
+            user: admin(),
+            token: AuthToken::OAuth2 {
+                token: AccessToken {
+                    token: "token".to_string(),
+                    client_id: "client".to_string(),
+                    expires_at: i64::MAX,
+                    issued_at: 0,
+                    user_id: "admin".to_string(),
+                    scopes: Some(ADMIN_SCOPE.to_string()),
+                    refresh_token: None,
+                },
+                first_party,
+            },
+        }
+    }
+
+    fn personal(scopes: &str) -> Auth {
+        Auth {
+            user: admin(),
+            token: AuthToken::Personal(PersonalAccessToken {
+                id: "id".to_string(),
+                name: "Token".to_string(),
+                user_id: "admin".to_string(),
+                scopes: Some(scopes.to_string()),
+                created_at: 0,
+                expires_at: None,
+                last_used_at: None,
+            }),
+        }
+    }
+
+    #[test]
+    fn first_party_token_is_admin() {
+        assert!(oauth2(true).require_admin().is_ok());
+        assert!(oauth2(true).require_first_party().is_ok());
+    }
+
+    #[test]
+    fn third_party_token_is_rejected() {
+        // Even when the administrator granted the client the admin scope
+        assert!(matches!(oauth2(false).require_admin(), Err(WebError::Forbidden)));
+        assert!(matches!(oauth2(false).require_first_party(), Err(WebError::Forbidden)));
+    }
+
+    #[test]
+    fn personal_token_needs_admin_scope() {
+        assert!(personal(ADMIN_SCOPE).require_admin().is_ok());
+        assert!(matches!(personal("profile").require_admin(), Err(WebError::Forbidden)));
+        assert!(matches!(personal(ADMIN_SCOPE).require_first_party(), Err(WebError::Forbidden)));
+    }
+
+    #[test]
+    fn non_admin_is_rejected() {
+        let mut auth = oauth2(true);
+        auth.user.is_admin = false;
+        assert!(matches!(auth.require_admin(), Err(WebError::Forbidden)));
+    }
+}
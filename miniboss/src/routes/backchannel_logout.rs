@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use actix_web::cookie::time::OffsetDateTime;
+use database::backchannel_logout::LogoutDelivery;
+use database::oauth2_client::OAuth2Client;
+use jsonwebtoken::{Algorithm, EncodingKey, Header};
+use serde::Serialize;
+use tracing::warn;
+
+use crate::routes::appdata::{WConfig, WDatabase};
+use crate::routes::error::WebResult;
+
+/// The event identifying a logout token
+/// [OIDC Back-Channel Logout Section 2.4](https://openid.net/specs/openid-connect-backchannel-1_0.html#LogoutToken)
+const LOGOUT_EVENT: &str = "http://schemas.openid.net/event/backchannel-logout";
+const MAX_ATTEMPTS: i32 = 5;
+/// Delay before the first retry, doubled after every failed attempt
+const INITIAL_RETRY_DELAY: Duration = Duration::from_secs(5);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Serialize)]
+struct LogoutTokenClaims<'a> {
+    iss: &'a str,
+    sub: &'a str,
+    aud: &'a str,
+    iat: i64,
+    jti: &'a str,
+    events: HashMap<&'static str, serde_json::Value>,
+}
+
+/// Notify every client holding tokens for the user that they have logged out.
+/// Notifications are sent in the background, and retried with backoff if they fail.
+/// Nothing is sent if ID tokens are not enabled, as clients could not verify the logout token.
+///
+/// # Errors
+///
+/// If a database error occurs while recording the notifications
+pub async fn notify_logout(database: &WDatabase, config: &WConfig, user_id: &str) -> WebResult<()> {
+    if config.oidc.is_none() {
+        return Ok(());
+    }
+
+    let clients = OAuth2Client::list_by_user_tokens(database, user_id)
+        .await?
+        .into_iter()
+        .filter(|client| client.backchannel_logout_uri.is_some());
+
+    for client in clients {
+        let delivery = LogoutDelivery::new(database, &client.client_id, user_id).await?;
+
+        let database = database.clone();
+        let config = config.clone();
+        tokio::spawn(async move {
+            deliver(&database, &config, &client, delivery).await;
+        });
+    }
+
+    Ok(())
+}
+
+async fn deliver(database: &WDatabase, config: &WConfig, client: &OAuth2Client, mut delivery: LogoutDelivery) {
+    let (Some(oidc), Some(uri)) = (&config.oidc, &client.backchannel_logout_uri) else {
+        return;
+    };
+
+    let claims = LogoutTokenClaims {
+        iss: &oidc.issuer,
+        sub: &delivery.user_id,
+        aud: &client.client_id,
+        iat: OffsetDateTime::now_utc().unix_timestamp(),
+        jti: &delivery.id,
+        events: HashMap::from([(LOGOUT_EVENT, serde_json::json!({}))]),
+    };
+
+    // Signed the same way as ID tokens
+    let token = match jsonwebtoken::encode(
+        &Header::new(Algorithm::HS256),
+        &claims,
+        &EncodingKey::from_secret(client.client_secret.as_bytes()),
+    ) {
+        Ok(token) => token,
+        Err(e) => {
+            warn!("Failed to sign logout token: {e}");
+            return;
+        }
+    };
+
+    let http = reqwest::Client::new();
+    let mut delay = INITIAL_RETRY_DELAY;
+
+    for attempt in 1..=MAX_ATTEMPTS {
+        let result = http
+            .post(uri)
+            .timeout(REQUEST_TIMEOUT)
+            .form(&[("logout_token", &token)])
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+
+        let error = result.err().map(|e| e.to_string());
+        let failed = error.is_some();
+
+        if let Err(e) = delivery.record_attempt(database, error).await {
+            warn!("Failed to record logout delivery: {e}");
+        }
+
+        if !failed {
+            return;
+        }
+
+        if attempt < MAX_ATTEMPTS {
+            tokio::time::sleep(delay).await;
+            delay *= 2;
+        }
+    }
+
+    warn!("Giving up on back-channel logout to client {} after {MAX_ATTEMPTS} attempts", client.client_id);
+}
@@ -1,5 +1,10 @@
+use actix_web::body::BoxBody;
+use actix_web::http::header::ContentType;
 use actix_web::http::StatusCode;
-use actix_web::ResponseError;
+use actix_web::{HttpResponse, ResponseError};
+use database::password_policy::PasswordPolicyViolation;
+use database::user::HashingError;
+use serde::Serialize;
 use thiserror::Error;
 
 pub type WebResult<T> = Result<T, WebError>;
@@ -16,10 +21,22 @@ pub enum WebError {
     InvalidInternalState,
     #[error("Forbidden")]
     Forbidden,
+    #[error("Too many requests")]
+    TooManyRequests,
+    #[error("The authorization has expired, start again from the application")]
+    AuthorizationExpired,
     #[error("{0}")]
     Database(#[from] database::driver::Error),
     #[error("Hashing error: {0}")]
-    Hashing(#[from] database::user::HashingError)
+    Hashing(HashingError),
+    #[error("{0}")]
+    PasswordPolicy(#[from] PasswordPolicyViolation),
+    #[error("TOTP error: {0}")]
+    Totp(#[from] database::totp::TotpError),
+    #[error("WebAuthn error: {0}")]
+    Webauthn(#[from] webauthn_rs::prelude::WebauthnError),
+    #[error("WebAuthn storage error: {0}")]
+    WebauthnStorage(#[from] database::webauthn::WebauthnStorageError),
 }
 
 impl ResponseError for WebError {
@@ -29,9 +46,46 @@ impl ResponseError for WebError {
             Self::BadRequest => StatusCode::BAD_REQUEST,
             Self::Unauthorized => StatusCode::UNAUTHORIZED,
             Self::Forbidden => StatusCode::FORBIDDEN,
+            Self::TooManyRequests => StatusCode::TOO_MANY_REQUESTS,
+            Self::AuthorizationExpired => StatusCode::GONE,
             Self::InvalidInternalState => StatusCode::INTERNAL_SERVER_ERROR,
             Self::Database(_) => StatusCode::INTERNAL_SERVER_ERROR,
             Self::Hashing(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::PasswordPolicy(_) => StatusCode::BAD_REQUEST,
+            Self::Totp(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::Webauthn(_) => StatusCode::BAD_REQUEST,
+            Self::WebauthnStorage(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse<BoxBody> {
+        match self {
+            // Tell the user which rule their password failed
+            Self::PasswordPolicy(violation) => {
+                #[derive(Serialize)]
+                struct Response<'a> {
+                    error: String,
+                    #[serde(flatten)]
+                    violation: &'a PasswordPolicyViolation,
+                }
+
+                HttpResponse::build(self.status_code()).json(&Response {
+                    error: violation.to_string(),
+                    violation,
+                })
+            }
+            _ => HttpResponse::build(self.status_code())
+                .content_type(ContentType::plaintext())
+                .body(self.to_string()),
         }
     }
-}
\ No newline at end of file
+}
+
+impl From<HashingError> for WebError {
+    fn from(value: HashingError) -> Self {
+        match value {
+            HashingError::Policy(violation) => Self::PasswordPolicy(violation),
+            other => Self::Hashing(other),
+        }
+    }
+}
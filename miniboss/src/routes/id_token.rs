@@ -0,0 +1,114 @@
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+use database::driver::Database;
+use database::oauth2_client::OAuth2Client;
+
+use crate::config::OidcConfig;
+use crate::routes::error::{WebError, WebResult};
+
+/// Authentication context class of logins with a second factor or passkey
+pub const ACR_MULTI_FACTOR: &str = "mfa";
+/// Authentication context class of logins with only a password
+pub const ACR_SINGLE_FACTOR: &str = "sfa";
+
+/// Claims of an OpenID Connect ID token.
+/// Tokens are signed with HS256, using the client's secret as key.
+/// [OIDC Core Section 2](https://openid.net/specs/openid-connect-core-1_0.html#IDToken)
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IdTokenClaims {
+    pub iss: String,
+    /// The user ID
+    pub sub: String,
+    /// The client ID
+    pub aud: String,
+    pub exp: i64,
+    pub iat: i64,
+    /// When the user last entered their credentials.
+    /// Unknown for authorizations from before this was recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth_time: Option<i64>,
+    /// [ACR_MULTI_FACTOR] or [ACR_SINGLE_FACTOR]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub acr: Option<String>,
+    /// The `nonce` of the authorization request
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+}
+
+/// How the user authenticated for the authorization an ID token is issued for
+pub struct Authentication<'a> {
+    pub auth_time: Option<i64>,
+    /// Whether the user completed a second factor, or used a passkey
+    pub multi_factor: bool,
+    /// Only included in the ID token issued for the authorization code, not on refresh
+    pub nonce: Option<&'a str>,
+}
+
+/// Create a signed ID token for the client
+pub fn issue_id_token(
+    config: &OidcConfig,
+    client: &OAuth2Client,
+    user_id: &str,
+    issued_at: i64,
+    expires_at: i64,
+    authentication: &Authentication<'_>,
+) -> jsonwebtoken::errors::Result<String> {
+    let acr = if authentication.multi_factor { ACR_MULTI_FACTOR } else { ACR_SINGLE_FACTOR };
+    let claims = IdTokenClaims {
+        iss: config.issuer.clone(),
+        sub: user_id.to_string(),
+        aud: client.client_id.clone(),
+        exp: expires_at,
+        iat: issued_at,
+        auth_time: authentication.auth_time,
+        acr: Some(acr.to_string()),
+        nonce: authentication.nonce.map(str::to_string),
+    };
+
+    jsonwebtoken::encode(
+        &Header::new(Algorithm::HS256),
+        &claims,
+        &EncodingKey::from_secret(client.client_secret.as_bytes()),
+    )
+}
+
+/// Verify an ID token previously issued by Miniboss, e.g. an `id_token_hint`.
+/// Expired tokens are accepted, as a hint is often used after the token has expired.
+/// Returns the client the token was issued to.
+///
+/// # Errors
+///
+/// [WebError::BadRequest] if the token was not issued by Miniboss, or the client no longer exists
+pub async fn verify_id_token(
+    database: &Database,
+    config: &OidcConfig,
+    token: &str,
+) -> WebResult<(OAuth2Client, IdTokenClaims)> {
+    // The key depends on the audience, which must be read before the signature can be verified
+    let mut insecure = Validation::new(Algorithm::HS256);
+    insecure.insecure_disable_signature_validation();
+    insecure.validate_exp = false;
+    insecure.validate_aud = false;
+
+    let unverified = jsonwebtoken::decode::<IdTokenClaims>(token, &DecodingKey::from_secret(&[]), &insecure)
+        .map_err(|_| WebError::BadRequest)?;
+
+    let client = OAuth2Client::get_by_client_id(database, &unverified.claims.aud)
+        .await?
+        .ok_or(WebError::BadRequest)?;
+
+    let mut validation = Validation::new(Algorithm::HS256);
+    validation.validate_exp = false;
+    validation.set_audience(&[&client.client_id]);
+    validation.set_issuer(&[&config.issuer]);
+    validation.set_required_spec_claims(&["iss", "sub", "aud"]);
+
+    let verified = jsonwebtoken::decode::<IdTokenClaims>(
+        token,
+        &DecodingKey::from_secret(client.client_secret.as_bytes()),
+        &validation,
+    )
+        .map_err(|_| WebError::BadRequest)?;
+
+    Ok((client, verified.claims))
+}
@@ -7,6 +7,11 @@ mod error;
 mod appdata;
 mod redirect;
 mod empty;
+mod throttle;
+mod scopes;
+mod session;
+mod id_token;
+mod backchannel_logout;
 mod v1;
 
 pub struct Router;
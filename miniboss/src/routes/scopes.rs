@@ -0,0 +1,81 @@
+use std::collections::HashSet;
+
+use database::driver::Database;
+use database::group::Group;
+use database::oauth2_client::{OAuth2Client, OAuth2PendingAuthorization};
+use database::scope::Scope;
+use database::user::User;
+
+use crate::routes::error::{WebError, WebResult};
+
+/// Grants access to the `groups` claim
+pub const GROUPS_SCOPE: &str = "groups";
+
+/// Grants a personal access token of an administrator access to the admin API
+pub const ADMIN_SCOPE: &str = "miniboss.admin";
+
+/// Parse an OAuth2 `scope` value.
+/// OAuth2 defines `scope` to be all scopes, seperated by a ' ' (space char)
+/// Where duplicates can be ignored.
+pub fn parse_scopes(scopes: Option<&str>) -> HashSet<String> {
+    scopes
+        .map(|s| s.split(' ').filter(|c| !c.is_empty()).map(|c| c.to_string()).collect::<HashSet<_>>())
+        .unwrap_or_default()
+}
+
+/// Check that the user is allowed all scopes requested in the authorization
+pub async fn check_permitted_scopes(
+    database: &Database,
+    user: &User,
+    authorization: &OAuth2PendingAuthorization,
+) -> WebResult<()> {
+    check_scopes_permitted(database, user, &parse_scopes(authorization.scopes().as_deref())).await
+}
+
+/// Check that the user is allowed all provided scopes
+pub async fn check_scopes_permitted(
+    database: &Database,
+    user: &User,
+    scopes: &HashSet<String>,
+) -> WebResult<()> {
+    // Admins may request any scope
+    if user.is_admin {
+        return Ok(());
+    }
+
+    // Scopes can be permitted directly, through any of the user's groups, or to everyone
+    let permitted_scopes = user.list_permitted_scopes(database).await?;
+    let group_scopes = Group::list_permitted_scopes_for_user(database, &user.user_id).await?;
+
+    let allowed_scopes = permitted_scopes
+        .into_iter()
+        .chain(group_scopes)
+        .chain(Scope::list_default(database).await?.into_iter().map(|scope| scope.name))
+        .collect::<HashSet<_>>();
+
+    let disallowed_scopes = scopes
+        .difference(&allowed_scopes)
+        .collect::<HashSet<_>>();
+
+    if !disallowed_scopes.is_empty() {
+        return Err(WebError::Forbidden);
+    }
+
+    Ok(())
+}
+
+/// Check that the client may request all provided scopes.
+/// Scopes which are not registered are never allowed.
+pub async fn client_may_request(
+    database: &Database,
+    client: &OAuth2Client,
+    scopes: &HashSet<String>,
+) -> WebResult<bool> {
+    let allowed_scopes = client
+        .list_allowed_scopes(database)
+        .await?
+        .into_iter()
+        .collect::<HashSet<_>>();
+
+    Ok(scopes.is_subset(&allowed_scopes))
+}
@@ -0,0 +1,100 @@
+use actix_web::cookie::time::Duration;
+use actix_web::cookie::{Cookie, SameSite};
+use actix_web::http::header;
+use actix_web::{CustomizeResponder, HttpRequest, Responder};
+use database::driver::Database;
+use database::oauth2_client::OAuth2PendingAuthorization;
+use database::session::Session;
+
+use crate::config::Config;
+use crate::routes::error::{WebError, WebResult};
+
+/// Get the user's single sign-on session from the session cookie, if they have a valid one
+pub async fn current_session(database: &Database, config: &Config, req: &HttpRequest) -> WebResult<Option<Session>> {
+    let token = match req.cookie(&config.session.cookie_name) {
+        Some(cookie) => cookie.value().to_string(),
+        None => return Ok(None),
+    };
+
+    Ok(Session::get_valid_by_token(database, &token).await?)
+}
+
+/// Start a single sign-on session for the user, who has just completed their login.
+/// `multi_factor` is whether the user completed a second factor or used a passkey.
+/// A session the browser already had is ended, as the user has authenticated again.
+/// The session cookie is set on the response.
+pub async fn start_session<T: Responder>(
+    database: &Database,
+    config: &Config,
+    req: &HttpRequest,
+    user_id: &str,
+    multi_factor: bool,
+    responder: T,
+) -> WebResult<CustomizeResponder<T>> {
+    if let Some(previous) = current_session(database, config, req).await? {
+        previous.delete(database).await?;
+    }
+
+    let (_, token) = Session::new(database, user_id, config.session.lifetime_seconds, multi_factor).await?;
+    let cookie = session_cookie(config, token, Duration::seconds(config.session.lifetime_seconds));
+
+    Ok(responder
+        .customize()
+        .append_header((header::SET_COOKIE, cookie.to_string())))
+}
+
+fn session_cookie(config: &Config, value: String, max_age: Duration) -> Cookie<'static> {
+    Cookie::build(config.session.cookie_name.clone(), value)
+        .path("/")
+        .http_only(true)
+        .secure(config.session.secure)
+        // Lax, as the cookie must be sent when a client redirects the user to `/authorize`
+        .same_site(SameSite::Lax)
+        .max_age(max_age)
+        .finish()
+}
+
+/// The value binding pending authorizations to this browser.
+/// The browser's existing value is kept, so authorizations started in other tabs remain valid.
+pub fn browser_binding(config: &Config, req: &HttpRequest) -> String {
+    req.cookie(&config.session.binding_cookie_name)
+        .map(|cookie| cookie.value().to_string())
+        .filter(|value| !value.is_empty())
+        .unwrap_or_else(OAuth2PendingAuthorization::generate_browser_binding)
+}
+
+/// Set the cookie binding pending authorizations to this browser.
+/// It is only kept for as long as the browser is open.
+pub fn set_browser_binding_cookie<T: Responder>(config: &Config, binding: String, responder: T) -> CustomizeResponder<T> {
+    let cookie = Cookie::build(config.session.binding_cookie_name.clone(), binding)
+        .path("/")
+        .http_only(true)
+        .secure(config.session.secure)
+        // Lax, as the cookie must be sent when the login UI navigates to `/authorization`
+        .same_site(SameSite::Lax)
+        .finish();
+
+    responder
+        .customize()
+        .append_header((header::SET_COOKIE, cookie.to_string()))
+}
+
+/// Check the pending authorization was started by this browser
+///
+/// # Errors
+///
+/// [WebError::Forbidden] if the binding cookie is missing or does not match
+pub fn check_browser_binding(config: &Config, req: &HttpRequest, authorization: &OAuth2PendingAuthorization) -> WebResult<()> {
+    match req.cookie(&config.session.binding_cookie_name) {
+        Some(cookie) if authorization.is_bound_to(cookie.value()) => Ok(()),
+        _ => Err(WebError::Forbidden),
+    }
+}
+
+/// Remove the session cookie from the browser
+pub fn clear_session_cookie<T: Responder>(config: &Config, responder: T) -> CustomizeResponder<T> {
+    let cookie = session_cookie(config, String::new(), Duration::ZERO);
+    responder
+        .customize()
+        .append_header((header::SET_COOKIE, cookie.to_string()))
+}
@@ -0,0 +1,100 @@
+use std::net::SocketAddr;
+
+use actix_web::HttpRequest;
+use database::driver::Database;
+use database::login_throttle::{LoginThrottle, ThrottleKind, ThrottlePolicy};
+
+use crate::config::Config;
+use crate::routes::error::{WebError, WebResult};
+
+/// A login attempt, subject to brute-force protection.
+/// Attempts are counted against both the account and the source IP address.
+pub struct LoginAttempt<'a> {
+    database: &'a Database,
+    policy: ThrottlePolicy,
+    account: String,
+    ip: Option<String>,
+}
+
+impl<'a> LoginAttempt<'a> {
+    /// Start a login attempt for the account identified by `username`.
+    /// The attempt is counted as failed right away, until it is marked as [succeeded](Self::succeeded).
+    ///
+    /// # Errors
+    ///
+    /// [WebError::TooManyRequests] if the account or IP address is locked or has to wait before trying again.
+    /// This is independent of whether the account exists.
+    pub async fn start(database: &'a Database, config: &Config, req: &HttpRequest, username: &str) -> WebResult<Self> {
+        let attempt = Self {
+            database,
+            policy: config.login_throttle.policy(),
+            account: username.trim().to_lowercase(),
+            ip: client_ip(req, config.login_throttle.trust_proxy_headers),
+        };
+
+        for (kind, subject) in attempt.subjects() {
+            if !LoginThrottle::try_attempt(database, kind, subject, &attempt.policy).await? {
+                return Err(WebError::TooManyRequests);
+            }
+        }
+
+        Ok(attempt)
+    }
+
+    /// The attempt failed. It was already counted when it started. Returns the error to respond with.
+    pub fn failed(self) -> WebError {
+        WebError::Unauthorized
+    }
+
+    /// Record the attempt as succeeded, resetting the failures of the account
+    /// and no longer counting it against the IP address.
+    pub async fn succeeded(self) -> WebResult<()> {
+        LoginThrottle::clear(self.database, ThrottleKind::Account, &self.account).await?;
+        self.forgive_ip().await
+    }
+
+    /// Record the first step of the login as succeeded.
+    /// The attempt keeps counting against the account until the second factor is completed.
+    pub async fn first_factor_succeeded(self) -> WebResult<()> {
+        self.forgive_ip().await
+    }
+
+    async fn forgive_ip(&self) -> WebResult<()> {
+        if let Some(ip) = &self.ip {
+            LoginThrottle::forgive_attempt(self.database, ThrottleKind::Ip, ip).await?;
+        }
+
+        Ok(())
+    }
+
+    /// The IP address comes first, so a blocked IP address does not add to the attempts on the account
+    fn subjects(&self) -> impl Iterator<Item = (ThrottleKind, &str)> {
+        [
+            self.ip.as_deref().map(|ip| (ThrottleKind::Ip, ip)),
+            Some((ThrottleKind::Account, self.account.as_str())),
+        ]
+            .into_iter()
+            .flatten()
+    }
+}
+
+/// Lift the lock on an account, and forget its failed attempts
+pub async fn unlock_account(database: &Database, username: &str) -> WebResult<()> {
+    LoginThrottle::clear(database, ThrottleKind::Account, &username.trim().to_lowercase()).await?;
+    Ok(())
+}
+
+fn client_ip(req: &HttpRequest, trust_proxy_headers: bool) -> Option<String> {
+    let addr = if trust_proxy_headers {
+        req.connection_info()
+            .realip_remote_addr()
+            .map(|addr| addr.to_string())
+    } else {
+        req.peer_addr().map(|addr| addr.to_string())
+    }?;
+
+    // Strip the port, if there is one
+    Some(addr.parse::<SocketAddr>()
+        .map(|addr| addr.ip().to_string())
+        .unwrap_or(addr))
+}
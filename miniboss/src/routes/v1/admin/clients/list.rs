@@ -0,0 +1,17 @@
+use actix_web::web;
+use database::oauth2_client::OAuth2Client;
+use crate::routes::appdata::WDatabase;
+use crate::routes::auth::Auth;
+use crate::routes::error::WebResult;
+use crate::routes::v1::admin::clients::Client;
+
+pub async fn list(database: WDatabase, auth: Auth) -> WebResult<web::Json<Vec<Client>>> {
+    auth.require_admin()?;
+
+    let mut clients = Vec::new();
+    for client in OAuth2Client::list(&database).await? {
+        clients.push(Client::load(&database, client).await?);
+    }
+
+    Ok(web::Json(clients))
+}
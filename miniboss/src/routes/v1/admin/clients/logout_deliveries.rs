@@ -0,0 +1,18 @@
+use actix_web::web;
+use database::backchannel_logout::LogoutDelivery;
+use crate::routes::appdata::WDatabase;
+use crate::routes::auth::Auth;
+use crate::routes::error::WebResult;
+use crate::routes::v1::admin::clients::get_client;
+
+/// Back-channel logout notifications sent to the client, newest first
+pub async fn logout_deliveries(
+    database: WDatabase,
+    auth: Auth,
+    client_id: web::Path<String>,
+) -> WebResult<web::Json<Vec<LogoutDelivery>>> {
+    auth.require_admin()?;
+
+    let client = get_client(&database, &client_id).await?;
+    Ok(web::Json(LogoutDelivery::list_by_client_id(&database, &client.client_id).await?))
+}
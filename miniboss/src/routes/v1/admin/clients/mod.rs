@@ -0,0 +1,66 @@
+use actix_route_config::Routable;
+use actix_web::web;
+use actix_web::web::ServiceConfig;
+use serde::Serialize;
+
+use database::driver::Database;
+use database::oauth2_client::OAuth2Client;
+use crate::routes::error::{WebError, WebResult};
+
+mod list;
+mod logout_deliveries;
+mod scopes;
+mod update;
+
+pub struct Router;
+
+impl Routable for Router {
+    fn configure(config: &mut ServiceConfig) {
+        config.service(web::scope("/clients")
+            .route("", web::get().to(list::list))
+            .route("/{client_id}", web::patch().to(update::update))
+            .route("/{client_id}/logout-deliveries", web::get().to(logout_deliveries::logout_deliveries))
+            .configure(scopes::Router::configure)
+        );
+    }
+}
+
+async fn get_client(database: &Database, client_id: &str) -> WebResult<OAuth2Client> {
+    OAuth2Client::get_by_client_id(database, client_id)
+        .await?
+        .ok_or(WebError::NotFound)
+}
+
+#[derive(Serialize)]
+pub struct Client {
+    client_id: String,
+    name: String,
+    redirect_uri: String,
+    is_internal: bool,
+    is_trusted: bool,
+    post_logout_redirect_uris: Vec<String>,
+    backchannel_logout_uri: Option<String>,
+    /// Overrides of the configured lifetimes, in seconds
+    access_token_lifetime: Option<i64>,
+    authorization_code_lifetime: Option<i64>,
+    refresh_token_lifetime: Option<i64>,
+}
+
+impl Client {
+    async fn load(database: &Database, client: OAuth2Client) -> WebResult<Self> {
+        let post_logout_redirect_uris = client.list_post_logout_redirect_uris(database).await?;
+
+        Ok(Self {
+            client_id: client.client_id,
+            name: client.name,
+            redirect_uri: client.redirect_uri,
+            is_internal: client.is_internal,
+            is_trusted: client.is_trusted,
+            post_logout_redirect_uris,
+            backchannel_logout_uri: client.backchannel_logout_uri,
+            access_token_lifetime: client.access_token_lifetime,
+            authorization_code_lifetime: client.authorization_code_lifetime,
+            refresh_token_lifetime: client.refresh_token_lifetime,
+        })
+    }
+}
@@ -0,0 +1,33 @@
+use actix_web::web;
+use serde::Deserialize;
+use crate::routes::appdata::WDatabase;
+use crate::routes::auth::Auth;
+use crate::routes::empty::Empty;
+use crate::routes::error::WebResult;
+use crate::routes::v1::admin::clients::get_client;
+use crate::routes::v1::admin::scopes::get_scope;
+
+#[derive(Deserialize)]
+pub struct Request {
+    scope: String,
+}
+
+/// Allow the client to request a registered scope
+pub async fn allow(
+    database: WDatabase,
+    auth: Auth,
+    client_id: web::Path<String>,
+    payload: web::Json<Request>,
+) -> WebResult<Empty> {
+    auth.require_admin()?;
+
+    let client = get_client(&database, &client_id).await?;
+    let scope = get_scope(&database, &payload.scope).await?;
+
+    let allowed_scopes = client.list_allowed_scopes(&database).await?;
+    if !allowed_scopes.contains(&scope.name) {
+        client.allow_scope(&database, &scope.name).await?;
+    }
+
+    Ok(Empty)
+}
@@ -0,0 +1,30 @@
+use actix_web::web;
+use serde::Deserialize;
+use crate::routes::appdata::WDatabase;
+use crate::routes::auth::Auth;
+use crate::routes::empty::Empty;
+use crate::routes::error::{WebError, WebResult};
+use crate::routes::v1::admin::clients::get_client;
+
+#[derive(Deserialize)]
+pub struct Path {
+    client_id: String,
+    scope: String,
+}
+
+pub async fn disallow(
+    database: WDatabase,
+    auth: Auth,
+    path: web::Path<Path>,
+) -> WebResult<Empty> {
+    auth.require_admin()?;
+
+    let client = get_client(&database, &path.client_id).await?;
+    let allowed_scopes = client.list_allowed_scopes(&database).await?;
+    if !allowed_scopes.contains(&path.scope) {
+        return Err(WebError::NotFound);
+    }
+
+    client.disallow_scope(&database, &path.scope).await?;
+    Ok(Empty)
+}
@@ -0,0 +1,17 @@
+use actix_web::web;
+use crate::routes::appdata::WDatabase;
+use crate::routes::auth::Auth;
+use crate::routes::error::WebResult;
+use crate::routes::v1::admin::clients::get_client;
+
+/// The scopes the client may request
+pub async fn list(
+    database: WDatabase,
+    auth: Auth,
+    client_id: web::Path<String>,
+) -> WebResult<web::Json<Vec<String>>> {
+    auth.require_admin()?;
+
+    let client = get_client(&database, &client_id).await?;
+    Ok(web::Json(client.list_allowed_scopes(&database).await?))
+}
@@ -0,0 +1,19 @@
+use actix_route_config::Routable;
+use actix_web::web;
+use actix_web::web::ServiceConfig;
+
+mod allow;
+mod disallow;
+mod list;
+
+pub struct Router;
+
+impl Routable for Router {
+    fn configure(config: &mut ServiceConfig) {
+        config.service(web::scope("/{client_id}/scopes")
+            .route("", web::get().to(list::list))
+            .route("", web::post().to(allow::allow))
+            .route("/{scope}", web::delete().to(disallow::disallow))
+        );
+    }
+}
@@ -0,0 +1,80 @@
+use std::collections::HashSet;
+use actix_web::web;
+use serde::Deserialize;
+use reqwest::Url;
+use crate::routes::appdata::WDatabase;
+use crate::routes::auth::Auth;
+use crate::routes::error::{WebError, WebResult};
+use crate::routes::v1::admin::clients::{get_client, Client};
+
+#[derive(Deserialize)]
+pub struct Request {
+    /// Trusted clients are authorized without asking the user for consent
+    is_trusted: Option<bool>,
+    /// Replaces the registered URIs
+    post_logout_redirect_uris: Option<HashSet<String>>,
+    /// Notified when a user logs out. An empty string removes it.
+    backchannel_logout_uri: Option<String>,
+    /// Overrides of the configured lifetimes, in seconds. `0` removes an override.
+    access_token_lifetime: Option<i64>,
+    authorization_code_lifetime: Option<i64>,
+    refresh_token_lifetime: Option<i64>,
+}
+
+/// Apply a lifetime override from the request to the current one
+fn lifetime_override(current: Option<i64>, requested: Option<i64>) -> WebResult<Option<i64>> {
+    match requested {
+        None => Ok(current),
+        Some(0) => Ok(None),
+        Some(seconds) if seconds > 0 => Ok(Some(seconds)),
+        Some(_) => Err(WebError::BadRequest),
+    }
+}
+
+pub async fn update(
+    database: WDatabase,
+    auth: Auth,
+    client_id: web::Path<String>,
+    payload: web::Json<Request>,
+) -> WebResult<web::Json<Client>> {
+    auth.require_admin()?;
+
+    let mut client = get_client(&database, &client_id).await?;
+    if let Some(is_trusted) = payload.is_trusted {
+        client.set_trusted(&database, is_trusted).await?;
+    }
+
+    if let Some(uris) = &payload.post_logout_redirect_uris {
+        if uris.iter().any(|uri| uri.is_empty() || uri.len() > 512) {
+            return Err(WebError::BadRequest);
+        }
+
+        client.set_post_logout_redirect_uris(&database, uris).await?;
+    }
+
+    if let Some(uri) = &payload.backchannel_logout_uri {
+        let uri = if uri.is_empty() {
+            None
+        } else {
+            let uri = Url::parse(uri).map_err(|_| WebError::BadRequest)?;
+            if !matches!(uri.scheme(), "http" | "https") {
+                return Err(WebError::BadRequest);
+            }
+
+            Some(uri.to_string())
+        };
+
+        client.set_backchannel_logout_uri(&database, uri).await?;
+    }
+
+    let access_token = lifetime_override(client.access_token_lifetime, payload.access_token_lifetime)?;
+    let authorization_code = lifetime_override(client.authorization_code_lifetime, payload.authorization_code_lifetime)?;
+    let refresh_token = lifetime_override(client.refresh_token_lifetime, payload.refresh_token_lifetime)?;
+    if (access_token, authorization_code, refresh_token)
+        != (client.access_token_lifetime, client.authorization_code_lifetime, client.refresh_token_lifetime)
+    {
+        client.set_token_lifetimes(&database, access_token, authorization_code, refresh_token).await?;
+    }
+
+    Ok(web::Json(Client::load(&database, client).await?))
+}
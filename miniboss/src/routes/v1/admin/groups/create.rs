@@ -0,0 +1,30 @@
+use actix_web::web;
+use serde::Deserialize;
+use database::group::Group;
+use crate::routes::appdata::WDatabase;
+use crate::routes::auth::Auth;
+use crate::routes::error::{WebError, WebResult};
+
+#[derive(Deserialize)]
+pub struct Request {
+    name: String,
+}
+
+pub async fn create(
+    database: WDatabase,
+    auth: Auth,
+    payload: web::Json<Request>,
+) -> WebResult<web::Json<Group>> {
+    auth.require_admin()?;
+    let payload = payload.into_inner();
+
+    if payload.name.is_empty() || payload.name.len() > 64 {
+        return Err(WebError::BadRequest);
+    }
+
+    if Group::get_by_name(&database, &payload.name).await?.is_some() {
+        return Err(WebError::BadRequest);
+    }
+
+    Ok(web::Json(Group::new(&database, payload.name).await?))
+}
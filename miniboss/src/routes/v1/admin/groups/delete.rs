@@ -0,0 +1,20 @@
+use actix_web::web;
+use crate::routes::appdata::WDatabase;
+use crate::routes::auth::Auth;
+use crate::routes::empty::Empty;
+use crate::routes::error::WebResult;
+use crate::routes::v1::admin::groups::get_group;
+
+/// Delete the group. Its members lose the scopes they were permitted through it.
+pub async fn delete(
+    database: WDatabase,
+    auth: Auth,
+    group_id: web::Path<String>,
+) -> WebResult<Empty> {
+    auth.require_admin()?;
+
+    let group = get_group(&database, &group_id).await?;
+    group.delete(&database).await?;
+
+    Ok(Empty)
+}
@@ -0,0 +1,10 @@
+use actix_web::web;
+use database::group::Group;
+use crate::routes::appdata::WDatabase;
+use crate::routes::auth::Auth;
+use crate::routes::error::WebResult;
+
+pub async fn list(database: WDatabase, auth: Auth) -> WebResult<web::Json<Vec<Group>>> {
+    auth.require_admin()?;
+    Ok(web::Json(Group::list(&database).await?))
+}
@@ -0,0 +1,33 @@
+use actix_web::web;
+use serde::Deserialize;
+use database::user::User;
+use crate::routes::appdata::WDatabase;
+use crate::routes::auth::Auth;
+use crate::routes::empty::Empty;
+use crate::routes::error::{WebError, WebResult};
+use crate::routes::v1::admin::groups::get_group;
+
+#[derive(Deserialize)]
+pub struct Request {
+    user_id: String,
+}
+
+pub async fn add(
+    database: WDatabase,
+    auth: Auth,
+    group_id: web::Path<String>,
+    payload: web::Json<Request>,
+) -> WebResult<Empty> {
+    auth.require_admin()?;
+
+    let group = get_group(&database, &group_id).await?;
+    let user = User::get_by_id(&database, &payload.user_id)
+        .await?
+        .ok_or(WebError::NotFound)?;
+
+    if !group.is_member(&database, &user.user_id).await? {
+        group.add_member(&database, &user.user_id).await?;
+    }
+
+    Ok(Empty)
+}
@@ -0,0 +1,17 @@
+use actix_web::web;
+use crate::routes::appdata::WDatabase;
+use crate::routes::auth::Auth;
+use crate::routes::error::WebResult;
+use crate::routes::v1::admin::groups::get_group;
+
+/// The IDs of all members of the group
+pub async fn list(
+    database: WDatabase,
+    auth: Auth,
+    group_id: web::Path<String>,
+) -> WebResult<web::Json<Vec<String>>> {
+    auth.require_admin()?;
+
+    let group = get_group(&database, &group_id).await?;
+    Ok(web::Json(group.list_members(&database).await?))
+}
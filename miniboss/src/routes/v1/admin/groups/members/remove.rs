@@ -0,0 +1,29 @@
+use actix_web::web;
+use serde::Deserialize;
+use crate::routes::appdata::WDatabase;
+use crate::routes::auth::Auth;
+use crate::routes::empty::Empty;
+use crate::routes::error::{WebError, WebResult};
+use crate::routes::v1::admin::groups::get_group;
+
+#[derive(Deserialize)]
+pub struct Path {
+    group_id: String,
+    user_id: String,
+}
+
+pub async fn remove(
+    database: WDatabase,
+    auth: Auth,
+    path: web::Path<Path>,
+) -> WebResult<Empty> {
+    auth.require_admin()?;
+
+    let group = get_group(&database, &path.group_id).await?;
+    if !group.is_member(&database, &path.user_id).await? {
+        return Err(WebError::NotFound);
+    }
+
+    group.remove_member(&database, &path.user_id).await?;
+    Ok(Empty)
+}
@@ -0,0 +1,33 @@
+use actix_route_config::Routable;
+use actix_web::web;
+use actix_web::web::ServiceConfig;
+
+use database::driver::Database;
+use database::group::Group;
+use crate::routes::error::{WebError, WebResult};
+
+mod create;
+mod delete;
+mod list;
+mod members;
+mod scopes;
+
+pub struct Router;
+
+impl Routable for Router {
+    fn configure(config: &mut ServiceConfig) {
+        config.service(web::scope("/groups")
+            .route("", web::get().to(list::list))
+            .route("", web::post().to(create::create))
+            .route("/{group_id}", web::delete().to(delete::delete))
+            .configure(members::Router::configure)
+            .configure(scopes::Router::configure)
+        );
+    }
+}
+
+async fn get_group(database: &Database, group_id: &str) -> WebResult<Group> {
+    Group::get_by_id(database, group_id)
+        .await?
+        .ok_or(WebError::NotFound)
+}
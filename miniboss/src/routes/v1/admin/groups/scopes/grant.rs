@@ -0,0 +1,33 @@
+use actix_web::web;
+use serde::Deserialize;
+use crate::routes::appdata::WDatabase;
+use crate::routes::auth::Auth;
+use crate::routes::empty::Empty;
+use crate::routes::error::WebResult;
+use crate::routes::v1::admin::groups::get_group;
+use crate::routes::v1::admin::scopes::get_scope;
+
+#[derive(Deserialize)]
+pub struct Request {
+    scope: String,
+}
+
+/// Permit all members of the group the scope
+pub async fn grant(
+    database: WDatabase,
+    auth: Auth,
+    group_id: web::Path<String>,
+    payload: web::Json<Request>,
+) -> WebResult<Empty> {
+    auth.require_admin()?;
+
+    let group = get_group(&database, &group_id).await?;
+    let scope = get_scope(&database, &payload.scope).await?;
+
+    let scopes = group.list_permitted_scopes(&database).await?;
+    if !scopes.contains(&scope.name) {
+        group.grant_permitted_scope(&database, &scope.name).await?;
+    }
+
+    Ok(Empty)
+}
@@ -0,0 +1,19 @@
+use actix_route_config::Routable;
+use actix_web::web;
+use actix_web::web::ServiceConfig;
+
+mod grant;
+mod list;
+mod remove;
+
+pub struct Router;
+
+impl Routable for Router {
+    fn configure(config: &mut ServiceConfig) {
+        config.service(web::scope("/{group_id}/scopes")
+            .route("", web::get().to(list::list))
+            .route("", web::post().to(grant::grant))
+            .route("/{scope}", web::delete().to(remove::remove))
+        );
+    }
+}
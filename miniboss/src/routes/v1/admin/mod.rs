@@ -0,0 +1,21 @@
+use actix_route_config::Routable;
+use actix_web::web;
+use actix_web::web::ServiceConfig;
+
+mod clients;
+mod groups;
+mod scopes;
+mod users;
+
+pub struct Router;
+
+impl Routable for Router {
+    fn configure(config: &mut ServiceConfig) {
+        config.service(web::scope("/admin")
+            .configure(clients::Router::configure)
+            .configure(groups::Router::configure)
+            .configure(scopes::Router::configure)
+            .configure(users::Router::configure)
+        );
+    }
+}
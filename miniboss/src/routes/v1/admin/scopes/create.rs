@@ -0,0 +1,35 @@
+use actix_web::web;
+use serde::Deserialize;
+use database::scope::Scope;
+use crate::routes::appdata::WDatabase;
+use crate::routes::auth::Auth;
+use crate::routes::error::{WebError, WebResult};
+
+#[derive(Deserialize)]
+pub struct Request {
+    name: String,
+    description: String,
+    #[serde(default)]
+    is_default: bool,
+}
+
+/// Register a new scope
+pub async fn create(
+    database: WDatabase,
+    auth: Auth,
+    payload: web::Json<Request>,
+) -> WebResult<web::Json<Scope>> {
+    auth.require_admin()?;
+    let payload = payload.into_inner();
+
+    // Scopes are space separated in OAuth2, so a scope itself can't contain one
+    if payload.name.is_empty() || payload.name.len() > 64 || payload.name.contains(' ') {
+        return Err(WebError::BadRequest);
+    }
+
+    if Scope::get_by_name(&database, &payload.name).await?.is_some() {
+        return Err(WebError::BadRequest);
+    }
+
+    Ok(web::Json(Scope::new(&database, payload.name, payload.description, payload.is_default).await?))
+}
@@ -0,0 +1,20 @@
+use actix_web::web;
+use crate::routes::appdata::WDatabase;
+use crate::routes::auth::Auth;
+use crate::routes::empty::Empty;
+use crate::routes::error::WebResult;
+use crate::routes::v1::admin::scopes::get_scope;
+
+/// Delete the scope. Clients can no longer request it.
+pub async fn delete(
+    database: WDatabase,
+    auth: Auth,
+    name: web::Path<String>,
+) -> WebResult<Empty> {
+    auth.require_admin()?;
+
+    let scope = get_scope(&database, &name).await?;
+    scope.delete(&database).await?;
+
+    Ok(Empty)
+}
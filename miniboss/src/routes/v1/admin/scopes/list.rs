@@ -0,0 +1,10 @@
+use actix_web::web;
+use database::scope::Scope;
+use crate::routes::appdata::WDatabase;
+use crate::routes::auth::Auth;
+use crate::routes::error::WebResult;
+
+pub async fn list(database: WDatabase, auth: Auth) -> WebResult<web::Json<Vec<Scope>>> {
+    auth.require_admin()?;
+    Ok(web::Json(Scope::list(&database).await?))
+}
@@ -0,0 +1,36 @@
+use actix_route_config::Routable;
+use actix_web::web;
+use actix_web::web::ServiceConfig;
+
+use database::driver::Database;
+use database::scope::Scope;
+use crate::routes::error::{WebError, WebResult};
+
+mod create;
+mod delete;
+mod list;
+mod update;
+
+pub struct Router;
+
+impl Routable for Router {
+    fn configure(config: &mut ServiceConfig) {
+        config.service(web::scope("/scopes")
+            .route("", web::get().to(list::list))
+            .route("", web::post().to(create::create))
+            .route("/{name}", web::patch().to(update::update))
+            .route("/{name}", web::delete().to(delete::delete))
+        );
+    }
+}
+
+/// Get a registered scope
+///
+/// # Errors
+///
+/// [WebError::NotFound] if the scope is not registered
+pub async fn get_scope(database: &Database, name: &str) -> WebResult<Scope> {
+    Scope::get_by_name(database, name)
+        .await?
+        .ok_or(WebError::NotFound)
+}
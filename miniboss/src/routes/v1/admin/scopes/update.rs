@@ -0,0 +1,30 @@
+use actix_web::web;
+use serde::Deserialize;
+use database::scope::Scope;
+use crate::routes::appdata::WDatabase;
+use crate::routes::auth::Auth;
+use crate::routes::error::WebResult;
+use crate::routes::v1::admin::scopes::get_scope;
+
+#[derive(Deserialize)]
+pub struct Request {
+    description: Option<String>,
+    is_default: Option<bool>,
+}
+
+pub async fn update(
+    database: WDatabase,
+    auth: Auth,
+    name: web::Path<String>,
+    payload: web::Json<Request>,
+) -> WebResult<web::Json<Scope>> {
+    auth.require_admin()?;
+    let payload = payload.into_inner();
+
+    let mut scope = get_scope(&database, &name).await?;
+    let description = payload.description.unwrap_or_else(|| scope.description.clone());
+    let is_default = payload.is_default.unwrap_or(scope.is_default);
+    scope.update(&database, description, is_default).await?;
+
+    Ok(web::Json(scope))
+}
@@ -0,0 +1,27 @@
+use actix_web::web;
+use database::session::Session;
+use database::user::User;
+use crate::routes::appdata::{WConfig, WDatabase};
+use crate::routes::auth::Auth;
+use crate::routes::backchannel_logout::notify_logout;
+use crate::routes::empty::Empty;
+use crate::routes::error::{WebError, WebResult};
+
+/// End all single sign-on sessions of the user, and notify the clients they are logged in to
+pub async fn logout(
+    database: WDatabase,
+    config: WConfig,
+    auth: Auth,
+    user_id: web::Path<String>,
+) -> WebResult<Empty> {
+    auth.require_admin()?;
+
+    let user = User::get_by_id(&database, &user_id)
+        .await?
+        .ok_or(WebError::NotFound)?;
+
+    Session::delete_by_user_id(&database, &user.user_id).await?;
+    notify_logout(&database, &config, &user.user_id).await?;
+
+    Ok(Empty)
+}
@@ -0,0 +1,17 @@
+use actix_route_config::Routable;
+use actix_web::web;
+use actix_web::web::ServiceConfig;
+
+mod logout;
+mod unlock;
+
+pub struct Router;
+
+impl Routable for Router {
+    fn configure(config: &mut ServiceConfig) {
+        config.service(web::scope("/users")
+            .route("/{user_id}/unlock", web::post().to(unlock::unlock))
+            .route("/{user_id}/logout", web::post().to(logout::logout))
+        );
+    }
+}
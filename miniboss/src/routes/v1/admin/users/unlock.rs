@@ -0,0 +1,24 @@
+use actix_web::web;
+use database::user::User;
+use crate::routes::appdata::WDatabase;
+use crate::routes::auth::Auth;
+use crate::routes::empty::Empty;
+use crate::routes::error::{WebError, WebResult};
+use crate::routes::throttle::unlock_account;
+
+/// Lift a lockout caused by too many failed login attempts
+pub async fn unlock(
+    database: WDatabase,
+    auth: Auth,
+    user_id: web::Path<String>,
+) -> WebResult<Empty> {
+    auth.require_admin()?;
+
+    let user = User::get_by_id(&database, &user_id)
+        .await?
+        .ok_or(WebError::NotFound)?;
+
+    unlock_account(&database, &user.email).await?;
+
+    Ok(Empty)
+}
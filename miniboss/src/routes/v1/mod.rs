@@ -5,6 +5,7 @@ use actix_web::web::ServiceConfig;
 mod oauth;
 mod user;
 mod clients;
+mod admin;
 
 pub struct Router;
 
@@ -14,6 +15,7 @@ impl Routable for Router {
             .configure(oauth::Router::configure)
             .configure(user::Router::configure)
             .configure(clients::Router::configure)
+            .configure(admin::Router::configure)
         );
     }
 }
\ No newline at end of file
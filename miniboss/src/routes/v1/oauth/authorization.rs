@@ -1,8 +1,13 @@
-use crate::routes::appdata::WDatabase;
+use crate::config::Config;
+use crate::routes::appdata::{WConfig, WDatabase};
 use crate::routes::error::{WebError, WebResult};
 use crate::routes::redirect::Redirect;
+use crate::routes::scopes::parse_scopes;
+use crate::routes::session::check_browser_binding;
 use actix_web::cookie::time::OffsetDateTime;
-use actix_web::web;
+use actix_web::{web, HttpRequest};
+use database::consent::Consent;
+use database::driver::Database;
 use database::oauth2_client::{
     AuthorizationType, OAuth2AuthorizationCodeCreationError, OAuth2Client,
     OAuth2PendingAuthorization,
@@ -14,21 +19,41 @@ use crate::routes::v1::oauth::{OAuth2AuthorizationResponse, OAuth2Error, OAuth2E
 pub struct Query {
     authorization: String,
     grant: bool,
+    /// Whether to remember the decision, so the user is not asked again
+    /// for the same client and scopes. Defaults to `true`.
+    remember: Option<bool>,
 }
 
 pub async fn authorization(
     database: WDatabase,
+    config: WConfig,
+    req: HttpRequest,
     query: web::Query<Query>,
 ) -> WebResult<OAuth2AuthorizationResponse<Redirect>> {
     let pending_authorization =
         OAuth2PendingAuthorization::get_by_id(&database, &query.authorization)
             .await?
             .ok_or(WebError::NotFound)?;
+    check_browser_binding(&config, &req, &pending_authorization)?;
 
-    let client = OAuth2Client::get_by_client_id(&database, &pending_authorization.client_id())
+    let client = OAuth2Client::get_by_client_id(&database, pending_authorization.client_id())
         .await?
         .ok_or(WebError::NotFound)?;
 
+    // Send the user back to the client, which can start a new authorization
+    if pending_authorization.is_expired() {
+        let kind = match pending_authorization.user_id() {
+            Some(_) => OAuth2ErrorKind::AccessDenied,
+            None => OAuth2ErrorKind::LoginRequired,
+        };
+
+        return Ok(OAuth2AuthorizationResponse::Err(OAuth2Error::new(
+            kind,
+            &client.redirect_uri,
+            pending_authorization.state().as_deref(),
+        )));
+    }
+
     if !query.grant {
         return Ok(OAuth2AuthorizationResponse::Err(OAuth2Error::new(
             OAuth2ErrorKind::AccessDenied,
@@ -37,11 +62,125 @@ pub async fn authorization(
         )));
     }
 
+    if query.remember.unwrap_or(true) {
+        let user_id = pending_authorization
+            .user_id()
+            .ok_or(WebError::InvalidInternalState)?;
+        let scopes = parse_scopes(pending_authorization.scopes().as_deref());
+
+        Consent::grant(&database, user_id, &client.client_id, &scopes).await?;
+    }
+
+    let redirect_uri = issue_authorization(&database, &config, &client, pending_authorization).await?;
+    Ok(OAuth2AuthorizationResponse::Ok(Redirect::new(redirect_uri)))
+}
+
+/// Get a pending authorization which has not expired yet.
+///
+/// # Errors
+///
+/// [WebError::NotFound] if it does not exist, [WebError::AuthorizationExpired] if it has expired
+pub async fn get_pending_authorization(database: &Database, id: &str) -> WebResult<OAuth2PendingAuthorization> {
+    let pending_authorization = OAuth2PendingAuthorization::get_by_id(database, id)
+        .await?
+        .ok_or(WebError::NotFound)?;
+
+    if pending_authorization.is_expired() {
+        return Err(WebError::AuthorizationExpired);
+    }
+
+    Ok(pending_authorization)
+}
+
+/// Response of the login endpoints
+#[derive(Serialize)]
+pub struct LoginResponse {
+    /// Whether the login is complete
+    status: bool,
+    /// Set if the client is trusted or the user has already consented to the authorization.
+    /// The code or token has been issued, and the user should be redirected here.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    redirect: Option<String>,
+    /// The second factors the user may complete the login with.
+    /// Empty if no second factor is required.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    second_factors: Vec<SecondFactor>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SecondFactor {
+    /// Complete the login via `/login/totp`
+    Totp,
+    /// Complete the login via `/login/webauthn/start` and `/login/webauthn/finish`
+    Webauthn,
+}
+
+impl LoginResponse {
+    /// The login is complete, `redirect` is the result of [complete_login]
+    pub fn complete(redirect: Option<String>) -> Self {
+        Self {
+            status: true,
+            redirect,
+            second_factors: Vec::new(),
+        }
+    }
+
+    pub fn second_factor_required(second_factors: Vec<SecondFactor>) -> Self {
+        Self {
+            status: false,
+            redirect: None,
+            second_factors,
+        }
+    }
+}
+
+/// Complete an authorization for which the user has just logged in.
+/// If the client is trusted, or the user has already consented to the requested scopes,
+/// the code or token is issued right away and the URI to redirect the user to is returned.
+/// This does not apply if the client asked for consent with `prompt=consent`.
+/// Otherwise, the user must be asked for consent through `/authorization`.
+///
+/// # Errors
+///
+/// If the authorization has not been authorized or a database error occurs
+pub async fn complete_login(
+    database: &Database,
+    config: &Config,
+    pending_authorization: OAuth2PendingAuthorization,
+) -> WebResult<Option<String>> {
+    let client = OAuth2Client::get_by_client_id(database, pending_authorization.client_id())
+        .await?
+        .ok_or(WebError::NotFound)?;
+    let user_id = pending_authorization
+        .user_id()
+        .ok_or(WebError::InvalidInternalState)?;
+    let scopes = parse_scopes(pending_authorization.scopes().as_deref());
+
+    if pending_authorization.force_consent() {
+        return Ok(None);
+    }
+
+    if !client.is_trusted && !Consent::covers(database, user_id, &client.client_id, &scopes).await? {
+        return Ok(None);
+    }
+
+    Ok(Some(issue_authorization(database, config, &client, pending_authorization).await?))
+}
+
+/// Issue the authorization code or access token for an authorized authorization.
+/// Returns the URI to redirect the user to.
+async fn issue_authorization(
+    database: &Database,
+    config: &Config,
+    client: &OAuth2Client,
+    pending_authorization: OAuth2PendingAuthorization,
+) -> WebResult<String> {
     let state = pending_authorization.state().clone();
     let redirect_uri = match pending_authorization.ty() {
         AuthorizationType::AuthorizationCode => {
             let authorization = client
-                .new_authorization_code(&database, pending_authorization)
+                .new_authorization_code(database, pending_authorization, &config.token_lifetimes.lifetimes())
                 .await
                 .map_err(|e| match e {
                     OAuth2AuthorizationCodeCreationError::Sqlx(e) => WebError::Database(e),
@@ -69,7 +208,7 @@ pub async fn authorization(
         }
         AuthorizationType::Implicit => {
             let access_token = client
-                .new_access_token(&database, pending_authorization)
+                .new_access_token(database, pending_authorization, &config.token_lifetimes.lifetimes())
                 .await
                 .map_err(|e| match e {
                     OAuth2AuthorizationCodeCreationError::Sqlx(e) => WebError::Database(e),
@@ -102,5 +241,5 @@ pub async fn authorization(
         }
     };
 
-    Ok(OAuth2AuthorizationResponse::Ok(Redirect::new(redirect_uri)))
-}
\ No newline at end of file
+    Ok(redirect_uri)
+}
@@ -1,8 +1,11 @@
 use crate::routes::appdata::WDatabase;
+use crate::routes::v1::oauth::authorization::get_pending_authorization;
 use crate::routes::error::{WebError, WebResult};
 use actix_web::web;
 use database::oauth2_client::{OAuth2Client, OAuth2PendingAuthorization};
+use database::scope::Scope;
 use serde::{Deserialize, Serialize};
+use crate::routes::scopes::parse_scopes;
 
 #[derive(Deserialize)]
 pub struct Query {
@@ -13,27 +16,48 @@ pub struct Query {
 pub struct Response {
     client_name: String,
     scopes: Option<String>,
+    /// The requested scopes, with their description to show to the user
+    scope_descriptions: Vec<ScopeDescription>,
+}
+
+#[derive(Serialize)]
+pub struct ScopeDescription {
+    name: String,
+    description: String,
 }
 
 pub async fn authorization_info(
     database: WDatabase,
     query: web::Query<Query>,
 ) -> WebResult<web::Json<Response>> {
-    let authorization = OAuth2PendingAuthorization::get_by_id(&database, &query.authorization)
-        .await?
-        .ok_or(WebError::NotFound)?;
+    let authorization = get_pending_authorization(&database, &query.authorization).await?;
 
     match &authorization {
         OAuth2PendingAuthorization::Authorized(_) => {}
         OAuth2PendingAuthorization::Unauthorized(_) => return Err(WebError::Unauthorized),
     }
 
-    let client = OAuth2Client::get_by_client_id(&database, &authorization.client_id())
+    let client = OAuth2Client::get_by_client_id(&database, authorization.client_id())
         .await?
         .ok_or(WebError::NotFound)?;
 
+    let mut scope_descriptions = Vec::new();
+    for name in parse_scopes(authorization.scopes().as_deref()) {
+        // Unregistered scopes are rejected in `authorize`, but the scope may have been deleted since
+        let description = Scope::get_by_name(&database, &name)
+            .await?
+            .map(|scope| scope.description)
+            .unwrap_or_else(|| name.clone());
+
+        scope_descriptions.push(ScopeDescription {
+            name,
+            description,
+        });
+    }
+
     Ok(web::Json(Response {
         client_name: client.name,
         scopes: authorization.scopes().clone(),
+        scope_descriptions,
     }))
 }
\ No newline at end of file
@@ -1,9 +1,18 @@
+use crate::config::Config;
 use crate::routes::appdata::{WConfig, WDatabase};
+use crate::routes::error::{WebError, WebResult};
 use crate::routes::redirect::Redirect;
-use actix_web::web;
-use database::oauth2_client::{AuthorizationType, OAuth2Client};
-use serde::Deserialize;
+use crate::routes::session::{browser_binding, current_session, set_browser_binding_cookie};
+use actix_web::{web, CustomizeResponder, HttpRequest};
+use database::driver::Database;
+use database::oauth2_client::{AuthorizationRequest, AuthorizationType, OAuth2Client, OAuth2PendingAuthorization};
+use database::session::Session;
+use database::user::User;
+use serde::{Deserialize, Serialize};
 use tracing::warn;
+use crate::routes::id_token::ACR_MULTI_FACTOR;
+use crate::routes::scopes::{check_permitted_scopes, client_may_request, parse_scopes};
+use crate::routes::v1::oauth::authorization::complete_login;
 use crate::routes::v1::oauth::{OAuth2AuthorizationResponse, OAuth2Error, OAuth2ErrorKind};
 
 #[derive(Deserialize)]
@@ -13,6 +22,19 @@ pub struct Query {
     redirect_uri: String,
     scope: Option<String>,
     state: Option<String>,
+    /// Space separated list of `none`, `login`, `consent` and `select_account`.
+    /// [OIDC Core Section 3.1.2.1](https://openid.net/specs/openid-connect-core-1_0.html#AuthRequest)
+    prompt: Option<String>,
+    /// Maximum seconds since the user last logged in.
+    /// If exceeded, the user must log in again.
+    max_age: Option<i64>,
+    /// The account the user likely wants to log in with. Passed on to the login UI.
+    login_hint: Option<String>,
+    /// Space separated, in order of preference.
+    /// With `mfa`, a session is only used if the user completed a second factor.
+    acr_values: Option<String>,
+    /// Included in the ID token, to let the client detect replays
+    nonce: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -27,81 +49,218 @@ pub enum ResponseType {
     Token,
 }
 
+/// The parsed `prompt` parameter
+#[derive(Default)]
+struct Prompt {
+    /// No user interface may be shown. If the user would have to log in or consent, an error is returned.
+    none: bool,
+    /// The user must log in again, even if they have a session
+    login: bool,
+    /// The user must be asked for consent, even if they consented before
+    consent: bool,
+    /// The user must be able to pick another account. Treated like `login`.
+    select_account: bool,
+}
+
+impl Prompt {
+    /// Parse the prompt. Unknown values are ignored.
+    /// Returns `None` if `none` is combined with any other value.
+    fn parse(prompt: Option<&str>) -> Option<Self> {
+        // Space separated, just like `scope`
+        let values = parse_scopes(prompt);
+        let prompt = Self {
+            none: values.contains("none"),
+            login: values.contains("login"),
+            consent: values.contains("consent"),
+            select_account: values.contains("select_account"),
+        };
+
+        if prompt.none && (prompt.login || prompt.consent || prompt.select_account) {
+            return None;
+        }
+
+        Some(prompt)
+    }
+}
+
+#[derive(Serialize)]
+struct LoginPageQuery<'a> {
+    authorization: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    login_hint: Option<&'a str>,
+}
+
+impl Query {
+    fn error(&self, kind: OAuth2ErrorKind) -> OAuth2AuthorizationResponse<Redirect> {
+        OAuth2AuthorizationResponse::Err(OAuth2Error::new(
+            kind,
+            &self.redirect_uri,
+            self.state.as_deref(),
+        ))
+    }
+
+    /// Check whether the user's session can be used instead of logging in.
+    /// Returns the error to respond with if it can't, and no user interface may be shown.
+    fn check_session(&self, prompt: &Prompt, session: &Session) -> Result<(), OAuth2ErrorKind> {
+        if prompt.login || prompt.select_account {
+            return Err(OAuth2ErrorKind::LoginRequired);
+        }
+
+        if let Some(max_age) = self.max_age {
+            if session.authentication_age() > max_age {
+                return Err(OAuth2ErrorKind::LoginRequired);
+            }
+        }
+
+        let acr_values = parse_scopes(self.acr_values.as_deref());
+        if acr_values.contains(ACR_MULTI_FACTOR) && !session.multi_factor {
+            return Err(OAuth2ErrorKind::InteractionRequired);
+        }
+
+        Ok(())
+    }
+}
+
+/// The pending authorization is bound to the browser with a cookie,
+/// so that it can only be logged in to and approved from the same browser.
 pub async fn authorize(
     database: WDatabase,
     config: WConfig,
+    req: HttpRequest,
     query: web::Query<Query>,
+) -> CustomizeResponder<OAuth2AuthorizationResponse<Redirect>> {
+    let binding = browser_binding(&config, &req);
+    let response = start_authorization(&database, &config, &req, &query, &binding).await;
+
+    set_browser_binding_cookie(&config, binding, response)
+}
+
+async fn start_authorization(
+    database: &Database,
+    config: &Config,
+    req: &HttpRequest,
+    query: &Query,
+    binding: &str,
 ) -> OAuth2AuthorizationResponse<Redirect> {
     // Get the OAuth2 client
-    let client = match OAuth2Client::get_by_client_id(&database, &query.client_id).await {
+    let client = match OAuth2Client::get_by_client_id(database, &query.client_id).await {
         Ok(Some(c)) => c,
-        Ok(None) => {
-            return OAuth2AuthorizationResponse::Err(OAuth2Error::new(
-                OAuth2ErrorKind::UnauthorizedClient,
-                &query.redirect_uri,
-                query.state.as_deref(),
-            ))
-        }
+        Ok(None) => return query.error(OAuth2ErrorKind::UnauthorizedClient),
         Err(e) => {
             warn!("{e}");
-            return OAuth2AuthorizationResponse::Err(OAuth2Error::new(
-                OAuth2ErrorKind::ServerError,
-                &query.redirect_uri,
-                query.state.as_deref(),
-            ));
+            return query.error(OAuth2ErrorKind::ServerError);
         }
     };
 
     // Check redirect URI
     if client.redirect_uri.ne(&query.redirect_uri) {
-        return OAuth2AuthorizationResponse::Err(OAuth2Error::new(
-            OAuth2ErrorKind::UnauthorizedClient,
-            &query.redirect_uri,
-            query.state.as_deref(),
-        ));
+        return query.error(OAuth2ErrorKind::UnauthorizedClient);
     }
 
-    let pending_authorization = match query.response_type {
-        ResponseType::Code => {
-            // Create authorization
-            client
-                .new_pending_authorization(
-                    &database,
-                    query.scope.clone(),
-                    query.state.clone(),
-                    AuthorizationType::AuthorizationCode,
-                )
-                .await
-        }
-        ResponseType::Token => {
-            // Create authorization
-            client
-                .new_pending_authorization(
-                    &database,
-                    query.scope.clone(),
-                    query.state.clone(),
-                    AuthorizationType::Implicit,
-                )
-                .await
+    let prompt = match Prompt::parse(query.prompt.as_deref()) {
+        Some(prompt) => prompt,
+        None => return query.error(OAuth2ErrorKind::InvalidRequest),
+    };
+
+    // Check the client may request the scopes
+    let scopes = parse_scopes(query.scope.as_deref());
+    match client_may_request(database, &client, &scopes).await {
+        Ok(true) => {}
+        Ok(false) => return query.error(OAuth2ErrorKind::InvalidScope),
+        Err(e) => {
+            warn!("{e}");
+            return query.error(OAuth2ErrorKind::ServerError);
         }
+    }
+
+    let ty = match query.response_type {
+        ResponseType::Code => AuthorizationType::AuthorizationCode,
+        ResponseType::Token => AuthorizationType::Implicit,
     };
 
+    // Create authorization
+    let pending_authorization = client
+        .new_pending_authorization(
+            database,
+            AuthorizationRequest {
+                scopes: query.scope.clone(),
+                state: query.state.clone(),
+                ty,
+                force_consent: prompt.consent,
+                nonce: query.nonce.clone(),
+            },
+            binding,
+            &config.token_lifetimes.lifetimes(),
+        )
+        .await;
+
     let pending_authorization = match pending_authorization {
         Ok(pa) => pa,
         Err(e) => {
             warn!("{e}");
-            return OAuth2AuthorizationResponse::Err(OAuth2Error::new(
-                OAuth2ErrorKind::ServerError,
-                &query.redirect_uri,
-                query.state.as_deref(),
-            ));
+            return query.error(OAuth2ErrorKind::ServerError);
         }
     };
 
-    // Redirect to login page
-    OAuth2AuthorizationResponse::Ok(Redirect::new(format!(
-        "{}?authorization={}",
+    let login_page = format!(
+        "{}?{}",
         config.http.ui_login_path,
-        pending_authorization.id(),
-    )))
-}
\ No newline at end of file
+        serde_qs::to_string(&LoginPageQuery {
+            authorization: pending_authorization.id(),
+            login_hint: query.login_hint.as_deref(),
+        })
+            .expect("Serializing query string"),
+    );
+
+    // Users with a single sign-on session don't have to log in again
+    let session = match current_session(database, config, req).await {
+        Ok(session) => session,
+        Err(e) => {
+            warn!("{e}");
+            return query.error(OAuth2ErrorKind::ServerError);
+        }
+    };
+
+    let session = match session.map(|session| (query.check_session(&prompt, &session), session)) {
+        Some((Ok(()), session)) => session,
+        Some((Err(kind), _)) if prompt.none => return query.error(kind),
+        None if prompt.none => return query.error(OAuth2ErrorKind::LoginRequired),
+        // Redirect to login page
+        _ => return OAuth2AuthorizationResponse::Ok(Redirect::new(login_page)),
+    };
+
+    match resume_session(database, config, &session, pending_authorization).await {
+        Ok(Some(redirect)) => OAuth2AuthorizationResponse::Ok(Redirect::new(redirect)),
+        Ok(None) if prompt.none => query.error(OAuth2ErrorKind::ConsentRequired),
+        // The user must still consent. The login page shows the consent screen,
+        // as the authorization is already authorized.
+        Ok(None) => OAuth2AuthorizationResponse::Ok(Redirect::new(login_page)),
+        Err(WebError::Forbidden) => query.error(OAuth2ErrorKind::AccessDenied),
+        Err(e) => {
+            warn!("{e}");
+            query.error(OAuth2ErrorKind::ServerError)
+        }
+    }
+}
+
+/// Authorize the pending authorization as the user of the session.
+/// Returns the URI to redirect to if no consent is required.
+async fn resume_session(
+    database: &Database,
+    config: &Config,
+    session: &Session,
+    pending_authorization: OAuth2PendingAuthorization,
+) -> WebResult<Option<String>> {
+    let user = User::get_by_id(database, &session.user_id)
+        .await?
+        .ok_or(WebError::Unauthorized)?;
+
+    check_permitted_scopes(database, &user, &pending_authorization).await?;
+
+    let pending_authorization = pending_authorization
+        .set_user_id(database, &user.user_id, session.authenticated_at, session.multi_factor)
+        .await
+        .map_err(|_| WebError::InvalidInternalState)?;
+
+    complete_login(database, config, pending_authorization).await
+}
@@ -0,0 +1,160 @@
+use actix_web::{web, CustomizeResponder, Either, HttpRequest, Responder};
+use database::oauth2_client::OAuth2Client;
+use serde::{Deserialize, Serialize};
+
+use crate::routes::appdata::{WConfig, WDatabase};
+use crate::routes::backchannel_logout::notify_logout;
+use crate::routes::empty::Empty;
+use crate::routes::error::{WebError, WebResult};
+use crate::routes::id_token::verify_id_token;
+use crate::routes::redirect::Redirect;
+use crate::routes::session::{clear_session_cookie, current_session};
+
+/// [OIDC RP-Initiated Logout Section 2](https://openid.net/specs/openid-connect-rpinitiated-1_0.html#RPLogout)
+#[derive(Deserialize, Serialize)]
+pub struct Params {
+    /// An ID token issued to the client, identifying the client and user
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id_token_hint: Option<String>,
+    /// Identifies the client if no `id_token_hint` is given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    client_id: Option<String>,
+    /// Must be registered for the client
+    #[serde(skip_serializing_if = "Option::is_none")]
+    post_logout_redirect_uri: Option<String>,
+    /// Passed back to the client through `post_logout_redirect_uri`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    state: Option<String>,
+}
+
+type Response = CustomizeResponder<Either<Redirect, Empty>>;
+
+pub async fn end_session_get(
+    database: WDatabase,
+    config: WConfig,
+    req: HttpRequest,
+    query: web::Query<Params>,
+) -> WebResult<Response> {
+    let params = query.into_inner();
+    if params.id_token_hint.is_some() {
+        return end_session(database, config, &req, params).await;
+    }
+
+    // Anyone can send the user here, so the user has to confirm they want to log out.
+    // The logout page does so through `end_session_post`.
+    let logout_page = config.http.ui_logout_path.as_ref().ok_or(WebError::BadRequest)?;
+    let location = format!(
+        "{logout_page}?{}",
+        serde_qs::to_string(&params).expect("Serializing query string"),
+    );
+
+    Ok(Either::Left(Redirect::new(location)).customize())
+}
+
+pub async fn end_session_post(
+    database: WDatabase,
+    config: WConfig,
+    req: HttpRequest,
+    form: web::Form<Params>,
+) -> WebResult<Response> {
+    let params = form.into_inner();
+
+    // Without an `id_token_hint`, this is the user confirming the logout on the logout page,
+    // which must not be forged by another site
+    if params.id_token_hint.is_none() && !is_same_site(&req) {
+        return Err(WebError::Forbidden);
+    }
+
+    end_session(database, config, &req, params).await
+}
+
+/// Whether the browser reports the request as coming from the same site, through `Sec-Fetch-Site`
+fn is_same_site(req: &HttpRequest) -> bool {
+    req.headers()
+        .get("Sec-Fetch-Site")
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value == "same-origin" || value == "same-site")
+}
+
+/// End the user's single sign-on session on behalf of a client.
+/// Clients with a back-channel logout URI are notified.
+/// If configured, the tokens the client holds for the user are revoked as well.
+async fn end_session(
+    database: WDatabase,
+    config: WConfig,
+    req: &HttpRequest,
+    params: Params,
+) -> WebResult<Response> {
+    let hint = match (&params.id_token_hint, &config.oidc) {
+        (Some(hint), Some(oidc)) => Some(verify_id_token(&database, oidc, hint).await?),
+        // We can't have issued the ID token
+        (Some(_), None) => return Err(WebError::BadRequest),
+        (None, _) => None,
+    };
+
+    let client = match (hint.as_ref(), &params.client_id) {
+        (Some((client, _)), Some(client_id)) if client.client_id.ne(client_id) => return Err(WebError::BadRequest),
+        (Some((client, _)), _) => Some(client.clone()),
+        (None, Some(client_id)) => Some(
+            OAuth2Client::get_by_client_id(&database, client_id)
+                .await?
+                .ok_or(WebError::BadRequest)?,
+        ),
+        (None, None) => None,
+    };
+
+    let redirect = match &params.post_logout_redirect_uri {
+        Some(uri) => {
+            let client = client.as_ref().ok_or(WebError::BadRequest)?;
+            if !client.list_post_logout_redirect_uris(&database).await?.contains(uri) {
+                return Err(WebError::BadRequest);
+            }
+
+            #[derive(Serialize)]
+            struct RedirectQuery<'a> {
+                #[serde(skip_serializing_if = "Option::is_none")]
+                state: Option<&'a str>,
+            }
+
+            let qs = serde_qs::to_string(&RedirectQuery {
+                state: params.state.as_deref(),
+            })
+                .expect("Serializing query string");
+
+            Some(if qs.is_empty() {
+                uri.clone()
+            } else {
+                format!("{uri}?{qs}")
+            })
+        }
+        None => None,
+    };
+
+    let hinted_user_id = hint.as_ref().map(|(_, claims)| &claims.sub);
+    let mut user_id = hinted_user_id.cloned();
+
+    if let Some(session) = current_session(&database, &config, req).await? {
+        // If the client thinks another user is logged in, the session is not the client's to end
+        if hinted_user_id.map(|sub| sub.eq(&session.user_id)).unwrap_or(true) {
+            let session_user_id = session.user_id.clone();
+            session.delete(&database).await?;
+
+            // Before revoking tokens, as the clients to notify are those holding tokens
+            notify_logout(&database, &config, &session_user_id).await?;
+            user_id = Some(session_user_id);
+        }
+    }
+
+    if config.session.revoke_tokens_on_logout {
+        if let (Some(client), Some(user_id)) = (&client, &user_id) {
+            client.revoke_tokens_for_user(&database, user_id).await?;
+        }
+    }
+
+    let response = match redirect {
+        Some(redirect) => Either::Left(Redirect::new(redirect)),
+        None => Either::Right(Empty),
+    };
+
+    Ok(clear_session_cookie(&config, response))
+}
@@ -1,10 +1,15 @@
 use crate::routes::appdata::{WConfig, WDatabase};
 use crate::routes::error::{WebError, WebResult};
-use actix_web::web;
-use database::oauth2_client::OAuth2PendingAuthorization;
+use crate::routes::session::{check_browser_binding, start_session};
+use crate::routes::v1::oauth::authorization::{complete_login, get_pending_authorization, LoginResponse, SecondFactor};
+use crate::routes::scopes::check_permitted_scopes;
+use crate::routes::throttle::LoginAttempt;
+use actix_web::cookie::time::OffsetDateTime;
+use actix_web::{web, CustomizeResponder, HttpRequest, Responder};
+use database::totp::UserTotp;
 use database::user::User;
-use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use database::webauthn::WebauthnCredential;
+use serde::Deserialize;
 
 #[derive(Deserialize)]
 pub struct Request {
@@ -13,68 +18,65 @@ pub struct Request {
     password: String,
 }
 
-#[derive(Serialize)]
-pub struct Response {
-    status: bool,
-}
-
 pub async fn login(
     database: WDatabase,
     config: WConfig,
+    req: HttpRequest,
     payload: web::Json<Request>,
-) -> WebResult<web::Json<Response>> {
-    let authorization = OAuth2PendingAuthorization::get_by_id(&database, &payload.authorization)
-        .await?
-        .ok_or(WebError::NotFound)?;
+) -> WebResult<CustomizeResponder<web::Json<LoginResponse>>> {
+    let authorization = get_pending_authorization(&database, &payload.authorization).await?;
+    check_browser_binding(&config, &req, &authorization)?;
+
+    let attempt = LoginAttempt::start(&database, &config, &req, &payload.username).await?;
 
-    let user = User::get_by_email(&database, &payload.username).await?
-        .ok_or(WebError::Unauthorized)?;
+    // Unknown accounts and wrong passwords must be indistinguishable
+    let user = match User::get_by_email(&database, &payload.username).await? {
+        Some(user) => user,
+        None => {
+            User::verify_dummy_password(&payload.password, &config.password_hashing()).await?;
+            return Err(attempt.failed());
+        }
+    };
 
-    if !user.verify_password(&payload.password, &config.password_pepper, &database).await? {
-        return Err(WebError::Unauthorized)
+    if !user.verify_password(&payload.password, &config.password_hashing(), &database).await? {
+        return Err(attempt.failed());
     }
 
-    // OAuth2 defines `scope` to be all scopes, seperated by a ' ' (space char)
-    // Where duplicates can be ignored.
-    let scope_set = authorization
-        .scopes()
-        .clone()
-        .map(|s| s.split(" ").map(|c| c.to_string()).collect::<HashSet<_>>())
-        .unwrap_or_default();
+    check_permitted_scopes(&database, &user, &authorization).await?;
 
-    if !user.is_admin {
-        let permitted_scopes =
-            HashSet::from_iter(user.list_permitted_scopes(&database).await?);
+    let mut second_factors = Vec::new();
+    if UserTotp::get_confirmed(&database, &user.user_id).await?.is_some() {
+        second_factors.push(SecondFactor::Totp);
+    }
 
-        let oidc_scopes = oidc_scopes();
-        let allowed_scopes = permitted_scopes
-            .union(&oidc_scopes)
-            .map(|c| c.to_string())
-            .collect::<HashSet<_>>();
+    if config.webauthn.is_some()
+        && !WebauthnCredential::list_by_user_id(&database, &user.user_id).await?.is_empty()
+    {
+        second_factors.push(SecondFactor::Webauthn);
+    }
 
-        let disallowed_scopes = scope_set
-            .difference(&allowed_scopes)
-            .collect::<HashSet<_>>();
+    // Failures of the account are only reset once the login is complete,
+    // so that the counter also covers attempts at the second factor
+    if !second_factors.is_empty() {
+        attempt.first_factor_succeeded().await?;
 
-        if !disallowed_scopes.is_empty() {
-            return Err(WebError::Forbidden);
-        }
+        authorization
+            .set_pending_user_id(&database, &user.user_id)
+            .await
+            .map_err(|_| WebError::BadRequest)?;
+
+        return Ok(web::Json(LoginResponse::second_factor_required(second_factors)).customize());
     }
 
-    authorization
-        .set_user_id(&database, &user.user_id)
+    attempt.succeeded().await?;
+
+    let authorization = authorization
+        .set_user_id(&database, &user.user_id, OffsetDateTime::now_utc().unix_timestamp(), false)
         .await
         .map_err(|_| WebError::BadRequest)?;
+    let redirect = complete_login(&database, &config, authorization).await?;
 
-    Ok(web::Json(Response {
-        status: true,
-    }))
-}
+    let response = web::Json(LoginResponse::complete(redirect));
 
-fn oidc_scopes() -> HashSet<String> {
-    HashSet::from_iter([
-        "openid".to_string(),
-        "profile".to_string(),
-        "email".to_string(),
-    ])
-}
\ No newline at end of file
+    start_session(&database, &config, &req, &user.user_id, false, response).await
+}
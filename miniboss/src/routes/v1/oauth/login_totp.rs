@@ -0,0 +1,66 @@
+use crate::routes::appdata::{WConfig, WDatabase};
+use crate::routes::error::{WebError, WebResult};
+use crate::routes::session::{check_browser_binding, start_session};
+use crate::routes::v1::oauth::authorization::{complete_login, get_pending_authorization, LoginResponse};
+use crate::routes::throttle::LoginAttempt;
+use crate::routes::scopes::check_permitted_scopes;
+use actix_web::cookie::time::OffsetDateTime;
+use actix_web::{web, CustomizeResponder, HttpRequest};
+use database::totp::{RecoveryCode, UserTotp};
+use database::user::User;
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct Request {
+    authorization: String,
+    /// Either a code from the user's authenticator, or one of their recovery codes
+    code: String,
+}
+
+/// Second step of the login, for users with TOTP enabled.
+/// The password must have been verified through `/login` first.
+pub async fn login_totp(
+    database: WDatabase,
+    config: WConfig,
+    req: HttpRequest,
+    payload: web::Json<Request>,
+) -> WebResult<CustomizeResponder<web::Json<LoginResponse>>> {
+    let authorization = get_pending_authorization(&database, &payload.authorization).await?;
+    check_browser_binding(&config, &req, &authorization)?;
+
+    let user_id = authorization
+        .pending_user_id()
+        .ok_or(WebError::Unauthorized)?;
+
+    let user = User::get_by_id(&database, user_id)
+        .await?
+        .ok_or(WebError::Unauthorized)?;
+
+    // Codes are counted against the same limits as passwords
+    let attempt = LoginAttempt::start(&database, &config, &req, &user.email).await?;
+
+    let mut totp = UserTotp::get_confirmed(&database, &user.user_id)
+        .await?
+        .ok_or(WebError::BadRequest)?;
+
+    let valid = totp.verify(&database, &payload.code).await?
+        || RecoveryCode::consume(&database, &user.user_id, &payload.code).await?;
+
+    if !valid {
+        return Err(attempt.failed());
+    }
+
+    attempt.succeeded().await?;
+
+    check_permitted_scopes(&database, &user, &authorization).await?;
+
+    let authorization = authorization
+        .set_user_id(&database, &user.user_id, OffsetDateTime::now_utc().unix_timestamp(), true)
+        .await
+        .map_err(|_| WebError::BadRequest)?;
+    let redirect = complete_login(&database, &config, authorization).await?;
+
+    let response = web::Json(LoginResponse::complete(redirect));
+
+    start_session(&database, &config, &req, &user.user_id, true, response).await
+}
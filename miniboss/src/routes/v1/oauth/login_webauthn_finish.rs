@@ -0,0 +1,91 @@
+use crate::routes::appdata::{WConfig, WDatabase};
+use crate::routes::error::{WebError, WebResult};
+use crate::routes::session::{check_browser_binding, start_session};
+use crate::routes::v1::oauth::authorization::{complete_login, get_pending_authorization, LoginResponse};
+use crate::routes::throttle::LoginAttempt;
+use crate::routes::scopes::check_permitted_scopes;
+use crate::routes::v1::oauth::login_webauthn_start::LoginChallenge;
+use actix_web::cookie::time::OffsetDateTime;
+use actix_web::{web, CustomizeResponder, HttpRequest};
+use database::user::User;
+use database::webauthn::{encode_credential_id, user_handle, WebauthnChallenge, WebauthnCredential};
+use serde::Deserialize;
+use webauthn_rs::prelude::{DiscoverableKey, PublicKeyCredential};
+
+#[derive(Deserialize)]
+pub struct Request {
+    authorization: String,
+    challenge_id: String,
+    /// The result of `navigator.credentials.get()`
+    credential: PublicKeyCredential,
+}
+
+pub async fn login_webauthn_finish(
+    database: WDatabase,
+    config: WConfig,
+    req: HttpRequest,
+    payload: web::Json<Request>,
+) -> WebResult<CustomizeResponder<web::Json<LoginResponse>>> {
+    let webauthn = config.webauthn.as_ref().ok_or(WebError::NotFound)?.build()?;
+
+    let authorization = get_pending_authorization(&database, &payload.authorization).await?;
+    check_browser_binding(&config, &req, &authorization)?;
+
+    let challenge: LoginChallenge = WebauthnChallenge::take(&database, &payload.challenge_id, authorization.id())
+        .await?
+        .ok_or(WebError::Unauthorized)?;
+
+    let credential_id = encode_credential_id(payload.credential.get_credential_id());
+    let mut credential = WebauthnCredential::get_by_credential_id(&database, &credential_id)
+        .await?
+        .ok_or(WebError::Unauthorized)?;
+
+    let user = User::get_by_id(&database, &credential.user_id)
+        .await?
+        .ok_or(WebError::Unauthorized)?;
+
+    let attempt = LoginAttempt::start(&database, &config, &req, &user.email).await?;
+
+    let result = match challenge {
+        LoginChallenge::SecondFactor(state) => {
+            // The assertion must come from the user who entered their password
+            if authorization.pending_user_id() != Some(&user.user_id) {
+                return Err(attempt.failed());
+            }
+
+            webauthn.finish_passkey_authentication(&payload.credential, &state)
+        }
+        LoginChallenge::Passwordless(state) => {
+            let handle = webauthn
+                .identify_discoverable_authentication(&payload.credential)
+                .map(|(handle, _)| handle);
+
+            if handle.ok() != Some(user_handle(&user.user_id)) {
+                return Err(attempt.failed());
+            }
+
+            let key = DiscoverableKey::from(credential.passkey()?);
+            webauthn.finish_discoverable_authentication(&payload.credential, state, &[key])
+        }
+    };
+
+    let result = match result {
+        Ok(result) => result,
+        Err(_) => return Err(attempt.failed()),
+    };
+
+    attempt.succeeded().await?;
+    credential.record_use(&database, &result).await?;
+
+    check_permitted_scopes(&database, &user, &authorization).await?;
+
+    let authorization = authorization
+        .set_user_id(&database, &user.user_id, OffsetDateTime::now_utc().unix_timestamp(), true)
+        .await
+        .map_err(|_| WebError::BadRequest)?;
+    let redirect = complete_login(&database, &config, authorization).await?;
+
+    let response = web::Json(LoginResponse::complete(redirect));
+
+    start_session(&database, &config, &req, &user.user_id, true, response).await
+}
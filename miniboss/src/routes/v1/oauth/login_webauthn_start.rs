@@ -0,0 +1,78 @@
+use crate::routes::appdata::{WConfig, WDatabase};
+use crate::routes::v1::oauth::authorization::get_pending_authorization;
+use crate::routes::session::check_browser_binding;
+use crate::routes::error::{WebError, WebResult};
+use actix_web::{web, HttpRequest};
+use database::oauth2_client::OAuth2PendingAuthorization;
+use database::webauthn::{WebauthnChallenge, WebauthnCredential};
+use serde::{Deserialize, Serialize};
+use webauthn_rs::prelude::{
+    DiscoverableAuthentication, PasskeyAuthentication, RequestChallengeResponse,
+};
+
+#[derive(Deserialize)]
+pub struct Request {
+    authorization: String,
+}
+
+#[derive(Serialize)]
+pub struct Response {
+    challenge_id: String,
+    /// To be passed to `navigator.credentials.get()`
+    options: RequestChallengeResponse,
+}
+
+/// The server side state of a WebAuthn login
+#[derive(Serialize, Deserialize)]
+pub enum LoginChallenge {
+    /// The user's password has been verified, the assertion is a second factor
+    SecondFactor(PasskeyAuthentication),
+    /// Passwordless login, the user is identified by the assertion
+    Passwordless(DiscoverableAuthentication),
+}
+
+/// Start a WebAuthn login for a pending authorization.
+/// If the user has already passed `/login`, their credentials are used as a second factor.
+/// Otherwise a passwordless login with a discoverable credential is started.
+pub async fn login_webauthn_start(
+    database: WDatabase,
+    config: WConfig,
+    req: HttpRequest,
+    payload: web::Json<Request>,
+) -> WebResult<web::Json<Response>> {
+    let webauthn = config.webauthn.as_ref().ok_or(WebError::NotFound)?.build()?;
+
+    let authorization = get_pending_authorization(&database, &payload.authorization).await?;
+    check_browser_binding(&config, &req, &authorization)?;
+
+    let (options, state) = match &authorization {
+        OAuth2PendingAuthorization::Authorized(_) => return Err(WebError::BadRequest),
+        OAuth2PendingAuthorization::Unauthorized(_) => match authorization.pending_user_id() {
+            Some(user_id) => {
+                let passkeys = WebauthnCredential::list_by_user_id(&database, user_id)
+                    .await?
+                    .iter()
+                    .map(|credential| credential.passkey())
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                if passkeys.is_empty() {
+                    return Err(WebError::BadRequest);
+                }
+
+                let (options, state) = webauthn.start_passkey_authentication(&passkeys)?;
+                (options, LoginChallenge::SecondFactor(state))
+            }
+            None => {
+                let (options, state) = webauthn.start_discoverable_authentication()?;
+                (options, LoginChallenge::Passwordless(state))
+            }
+        },
+    };
+
+    let challenge_id = WebauthnChallenge::store(&database, authorization.id(), &state).await?;
+
+    Ok(web::Json(Response {
+        challenge_id,
+        options,
+    }))
+}
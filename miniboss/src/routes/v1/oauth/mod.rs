@@ -4,14 +4,19 @@ use actix_web::body::BoxBody;
 use actix_web::{HttpRequest, HttpResponse, Responder, ResponseError, web};
 use actix_web::http::StatusCode;
 use actix_web::web::ServiceConfig;
+use database::driver::Database;
 use serde::Serialize;
 
 mod authorize;
 mod token;
 mod login;
+mod login_totp;
+mod login_webauthn_start;
+mod login_webauthn_finish;
 mod token_info;
 mod authorization;
 mod authorization_info;
+mod end_session;
 
 pub struct Router;
 
@@ -20,10 +25,15 @@ impl Routable for Router {
         config.service(web::scope("/oauth")
             .route("/authorize", web::get().to(authorize::authorize))
             .route("/login", web::post().to(login::login))
-            .route("/token", web::post().to(token::token))
+            .route("/login/totp", web::post().to(login_totp::login_totp))
+            .route("/login/webauthn/start", web::post().to(login_webauthn_start::login_webauthn_start))
+            .route("/login/webauthn/finish", web::post().to(login_webauthn_finish::login_webauthn_finish))
+            .route("/token", web::post().to(token::token::<Database>))
             .route("/token-info", web::get().to(token_info::token_info))
             .route("/authorization-info", web::get().to(authorization_info::authorization_info))
             .route("/authorization", web::get().to(authorization::authorization))
+            .route("/logout", web::get().to(end_session::end_session_get))
+            .route("/logout", web::post().to(end_session::end_session_post))
         );
     }
 }
@@ -94,6 +104,12 @@ pub enum OAuth2ErrorKind {
     ServerError,
     InvalidGrant,
     UnsupportedGrantType,
+    /// `prompt=none` was requested, but the user must log in
+    LoginRequired,
+    /// `prompt=none` was requested, but the user must consent
+    ConsentRequired,
+    /// `prompt=none` was requested, but the user must otherwise interact
+    InteractionRequired,
 }
 
 impl Display for OAuth2ErrorKind {
@@ -110,6 +126,9 @@ impl Display for OAuth2ErrorKind {
                 Self::ServerError => "server_error",
                 Self::InvalidGrant => "invalid_grant",
                 Self::UnsupportedGrantType => "unsupported_grant_type",
+                Self::LoginRequired => "login_required",
+                Self::ConsentRequired => "consent_required",
+                Self::InteractionRequired => "interaction_required",
             }
         )
     }
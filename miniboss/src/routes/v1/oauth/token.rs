@@ -1,8 +1,10 @@
-use crate::routes::appdata::WDatabase;
-use actix_web::cookie::time;
+use crate::config::Config;
+use crate::routes::appdata::WConfig;
+use crate::routes::id_token::{issue_id_token, Authentication};
 use actix_web::cookie::time::OffsetDateTime;
 use actix_web::web;
-use database::oauth2_client::{OAuth2AuthorizationCode, OAuth2Client, RefreshToken};
+use database::oauth2_client::{AccessToken, OAuth2AuthorizationCode, OAuth2Client};
+use database::repository::Storage;
 use serde::{Deserialize, Serialize};
 use tap::TapFallible;
 use tracing::warn;
@@ -33,13 +35,20 @@ pub struct Response {
     expires_in: i64,
     refresh_token: String,
     scope: String,
+    /// Only issued if the `openid` scope was granted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id_token: Option<String>,
 }
 
-pub async fn token(
-    database: WDatabase,
+/// Generic over the storage, so it can be tested against an in-memory one.
+/// Registered with [Database](database::driver::Database).
+pub async fn token<S: Storage>(
+    database: web::Data<S>,
+    config: WConfig,
     form: web::Form<Form>,
 ) -> Result<web::Json<Response>, OAuth2ErrorKind> {
-    let client = OAuth2Client::get_by_client_id(&database, &form.client_id)
+    let client = database
+        .get_client(&form.client_id)
         .await
         .tap_err(|e| warn!("{e}"))
         .map_err(|_| OAuth2ErrorKind::ServerError)?
@@ -60,7 +69,8 @@ pub async fn token(
                 None => return Err(OAuth2ErrorKind::InvalidRequest),
             };
 
-            let authorization = OAuth2AuthorizationCode::get_by_code(&database, code)
+            let authorization = database
+                .get_authorization_code(code)
                 .await
                 .tap_err(|e| warn!("{e}"))
                 .map_err(|_| OAuth2ErrorKind::ServerError)?
@@ -70,21 +80,47 @@ pub async fn token(
                 return Err(OAuth2ErrorKind::InvalidGrant);
             }
 
+            if authorization.is_consumed() {
+                return Err(revoke_replayed_code(&**database, &client, &authorization).await);
+            }
+
             if OffsetDateTime::now_utc().unix_timestamp() > authorization.expires_at {
                 return Err(OAuth2ErrorKind::InvalidGrant);
             }
 
-            let (atoken, rtoken) = client
-                .new_token_pair(&database, authorization)
+            let pair = database
+                .create_token_pair(&client, authorization.clone(), &config.token_lifetimes.lifetimes())
                 .await
                 .tap_err(|e| warn!("{e}"))
                 .map_err(|_| OAuth2ErrorKind::ServerError)?;
 
+            let (atoken, rtoken) = match pair {
+                Some(pair) => pair,
+                // Redeemed by a concurrent request, which is a replay just the same
+                None => {
+                    let authorization = database
+                        .get_authorization_code(code)
+                        .await
+                        .tap_err(|e| warn!("{e}"))
+                        .map_err(|_| OAuth2ErrorKind::ServerError)?
+                        .ok_or(OAuth2ErrorKind::InvalidGrant)?;
+
+                    return Err(revoke_replayed_code(&**database, &client, &authorization).await);
+                }
+            };
+
+            let id_token = id_token(&config, &client, &atoken, &Authentication {
+                auth_time: authorization.auth_time,
+                multi_factor: authorization.multi_factor,
+                nonce: authorization.nonce.as_deref(),
+            })?;
+
             Ok(web::Json(Response {
                 access_token: atoken.token,
                 token_type: "bearer".to_string(),
+                id_token,
                 scope: atoken.scopes.unwrap_or_default(),
-                expires_in: time::OffsetDateTime::now_utc().unix_timestamp() - atoken.expires_at,
+                expires_in: atoken.expires_at - OffsetDateTime::now_utc().unix_timestamp(),
                 refresh_token: rtoken.token,
             }))
         }
@@ -94,29 +130,302 @@ pub async fn token(
                 None => return Err(OAuth2ErrorKind::InvalidRequest),
             };
 
-            let rtoken = RefreshToken::get_by_token(&database, &rtoken)
+            let rtoken = database
+                .get_refresh_token(rtoken)
                 .await
                 .tap_err(|e| warn!("{e}"))
                 .map_err(|_| OAuth2ErrorKind::ServerError)?
                 .ok_or(OAuth2ErrorKind::InvalidGrant)?;
 
-            if client.client_id.ne(&rtoken.client_id) {
+            if client.client_id.ne(&rtoken.client_id) || rtoken.is_expired() {
                 return Err(OAuth2ErrorKind::InvalidGrant);
             }
 
-            let atoken = client
-                .refresh_access_token(&database, &rtoken)
+            let atoken = database
+                .refresh_access_token(&client, &rtoken, &config.token_lifetimes.lifetimes())
                 .await
                 .tap_err(|e| warn!("{e}"))
                 .map_err(|_| OAuth2ErrorKind::ServerError)?;
 
+            let id_token = id_token(&config, &client, &atoken, &Authentication {
+                auth_time: rtoken.auth_time,
+                multi_factor: rtoken.multi_factor,
+                nonce: None,
+            })?;
+
             Ok(web::Json(Response {
                 access_token: atoken.token,
                 token_type: "bearer".to_string(),
+                id_token,
                 expires_in: atoken.expires_at - OffsetDateTime::now_utc().unix_timestamp(),
                 scope: atoken.scopes.unwrap_or_default(),
                 refresh_token: rtoken.token,
             }))
         }
     }
-}
\ No newline at end of file
+}
+
+/// Revoke the tokens issued for an authorization code which was redeemed more than once.
+/// The code may have been intercepted, so the tokens issued for it can't be trusted either.
+/// Returns the error to respond with.
+async fn revoke_replayed_code<S: Storage>(database: &S, client: &OAuth2Client, code: &OAuth2AuthorizationCode) -> OAuth2ErrorKind {
+    warn!("Authorization code for client {} was used more than once, revoking the tokens issued for it", client.client_id);
+
+    match database.revoke_authorization_code_tokens(code).await {
+        Ok(()) => OAuth2ErrorKind::InvalidGrant,
+        Err(e) => {
+            warn!("{e}");
+            OAuth2ErrorKind::ServerError
+        }
+    }
+}
+
+/// Issue an ID token alongside the access token, if the client requested the `openid` scope
+/// and ID tokens are enabled
+fn id_token(
+    config: &Config,
+    client: &OAuth2Client,
+    atoken: &AccessToken,
+    authentication: &Authentication<'_>,
+) -> Result<Option<String>, OAuth2ErrorKind> {
+    let oidc = match &config.oidc {
+        Some(oidc) => oidc,
+        None => return Ok(None),
+    };
+
+    if !atoken.scopes().contains("openid") {
+        return Ok(None);
+    }
+
+    issue_id_token(oidc, client, &atoken.user_id, atoken.issued_at, atoken.expires_at, authentication)
+        .tap_err(|e| warn!("{e}"))
+        .map(Some)
+        .map_err(|_| OAuth2ErrorKind::ServerError)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{test, web, App};
+    use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+    use database::oauth2_client::{AuthorizationRequest, AuthorizationType, OAuth2AuthorizationCode, OAuth2Client};
+    use database::repository::{
+        AuthorizationCodeRepository, MemoryStorage, PendingAuthorizationRepository, TokenRepository,
+    };
+    use serde_json::{json, Value};
+
+    use crate::config::Config;
+    use crate::routes::id_token::{IdTokenClaims, ACR_MULTI_FACTOR};
+
+    use super::token;
+
+    const CLIENT_ID: &str = "client";
+    const CLIENT_SECRET: &str = "secret";
+    const REDIRECT_URI: &str = "https://client.example/callback";
+
+    fn config() -> web::Data<Config> {
+        let config = serde_json::from_value(json!({
+            "http": { "ui_login_path": "/login" },
+            "database": { "database": ":memory:" },
+            "default_client": { "redirect_uri": REDIRECT_URI },
+            "password_pepper": "pepper",
+            "oidc": { "issuer": "https://miniboss.example" },
+        }))
+        .expect("Parsing test config");
+
+        web::Data::new(config)
+    }
+
+    fn client() -> OAuth2Client {
+        OAuth2Client {
+            name: "Test".to_string(),
+            redirect_uri: REDIRECT_URI.to_string(),
+            client_id: CLIENT_ID.to_string(),
+            client_secret: CLIENT_SECRET.to_string(),
+            is_internal: false,
+            is_trusted: true,
+            backchannel_logout_uri: None,
+            access_token_lifetime: None,
+            authorization_code_lifetime: None,
+            refresh_token_lifetime: None,
+        }
+    }
+
+    const AUTH_TIME: i64 = 1_700_000_000;
+
+    fn request(scopes: &str, nonce: Option<&str>) -> AuthorizationRequest {
+        AuthorizationRequest {
+            scopes: Some(scopes.to_string()),
+            state: None,
+            ty: AuthorizationType::AuthorizationCode,
+            force_consent: false,
+            nonce: nonce.map(str::to_string),
+        }
+    }
+
+    /// Storage with the test client, and an authorization code issued to it
+    async fn storage_with_code(config: &Config) -> (web::Data<MemoryStorage>, OAuth2AuthorizationCode) {
+        storage_with_code_for(config, request("profile", None)).await
+    }
+
+    async fn storage_with_code_for(config: &Config, request: AuthorizationRequest) -> (web::Data<MemoryStorage>, OAuth2AuthorizationCode) {
+        let storage = MemoryStorage::default();
+        let client = client();
+        storage.insert_client(client.clone());
+
+        let pending = storage
+            .create_pending_authorization(&client, request, "binding", &config.token_lifetimes.lifetimes())
+            .await
+            .unwrap();
+        let pending = storage.authorize_pending_authorization(pending, "user", AUTH_TIME, true).await.unwrap();
+        let code = storage
+            .create_authorization_code(&client, pending, &config.token_lifetimes.lifetimes())
+            .await
+            .unwrap();
+
+        (web::Data::new(storage), code)
+    }
+
+    async fn request_token(
+        storage: &web::Data<MemoryStorage>,
+        config: &web::Data<Config>,
+        form: &[(&str, &str)],
+    ) -> (u16, Value) {
+        let app = test::init_service(
+            App::new()
+                .app_data(storage.clone())
+                .app_data(config.clone())
+                .route("/token", web::post().to(token::<MemoryStorage>)),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/token")
+            .set_form(form)
+            .to_request();
+        let response = test::call_service(&app, req).await;
+        let status = response.status().as_u16();
+
+        (status, test::read_body_json(response).await)
+    }
+
+    fn code_form(code: &str) -> [(&str, &str); 5] {
+        [
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("client_id", CLIENT_ID),
+            ("client_secret", CLIENT_SECRET),
+            ("redirect_uri", REDIRECT_URI),
+        ]
+    }
+
+    fn refresh_form(refresh_token: &str) -> [(&str, &str); 5] {
+        [
+            ("grant_type", "refresh_token"),
+            ("refresh_token", refresh_token),
+            ("client_id", CLIENT_ID),
+            ("client_secret", CLIENT_SECRET),
+            ("redirect_uri", REDIRECT_URI),
+        ]
+    }
+
+    fn token_field<'a>(body: &'a Value, field: &str) -> &'a str {
+        body[field].as_str().expect("Token field in response")
+    }
+
+    fn id_token_claims(body: &Value) -> IdTokenClaims {
+        let mut validation = Validation::new(Algorithm::HS256);
+        validation.set_audience(&[CLIENT_ID]);
+
+        jsonwebtoken::decode(token_field(body, "id_token"), &DecodingKey::from_secret(CLIENT_SECRET.as_bytes()), &validation)
+            .expect("Valid ID token")
+            .claims
+    }
+
+    #[actix_web::test]
+    async fn exchanges_code_for_tokens() {
+        let config = config();
+        let (storage, code) = storage_with_code(&config).await;
+
+        let (status, body) = request_token(&storage, &config, &code_form(&code.code)).await;
+        assert_eq!(status, 200);
+        assert_eq!(body["scope"], "profile");
+        assert!(body["expires_in"].as_i64().unwrap() > 0);
+
+        let atoken = storage.get_access_token(token_field(&body, "access_token")).await.unwrap().unwrap();
+        assert_eq!(atoken.user_id, "user");
+        assert!(storage.get_refresh_token(token_field(&body, "refresh_token")).await.unwrap().is_some());
+    }
+
+    #[actix_web::test]
+    async fn rejects_wrong_client_secret() {
+        let config = config();
+        let (storage, code) = storage_with_code(&config).await;
+
+        let mut form = code_form(&code.code);
+        form[3] = ("client_secret", "wrong");
+
+        let (status, body) = request_token(&storage, &config, &form).await;
+        assert_eq!(status, 400);
+        assert_eq!(body["error"], "unauthorized_client");
+    }
+
+    #[actix_web::test]
+    async fn refreshes_access_token() {
+        let config = config();
+        let (storage, code) = storage_with_code(&config).await;
+
+        let (_, body) = request_token(&storage, &config, &code_form(&code.code)).await;
+        let (status, refreshed) = request_token(&storage, &config, &refresh_form(token_field(&body, "refresh_token"))).await;
+        assert_eq!(status, 200);
+        assert_ne!(token_field(&refreshed, "access_token"), token_field(&body, "access_token"));
+        assert_eq!(refreshed["refresh_token"], body["refresh_token"]);
+        assert_eq!(refreshed["scope"], "profile");
+
+        assert!(storage.get_access_token(token_field(&refreshed, "access_token")).await.unwrap().is_some());
+    }
+
+    #[actix_web::test]
+    async fn replayed_code_revokes_issued_tokens() {
+        let config = config();
+        let (storage, code) = storage_with_code(&config).await;
+
+        let (_, body) = request_token(&storage, &config, &code_form(&code.code)).await;
+        let (_, refreshed) = request_token(&storage, &config, &refresh_form(token_field(&body, "refresh_token"))).await;
+
+        let (status, replay) = request_token(&storage, &config, &code_form(&code.code)).await;
+        assert_eq!(status, 400);
+        assert_eq!(replay["error"], "invalid_grant");
+
+        // Including the access token issued with the refresh token
+        for atoken in [token_field(&body, "access_token"), token_field(&refreshed, "access_token")] {
+            assert!(storage.get_access_token(atoken).await.unwrap().is_none());
+        }
+        assert!(storage.get_refresh_token(token_field(&body, "refresh_token")).await.unwrap().is_none());
+
+        let (status, _) = request_token(&storage, &config, &refresh_form(token_field(&body, "refresh_token"))).await;
+        assert_eq!(status, 400);
+    }
+
+    #[actix_web::test]
+    async fn id_token_reports_authentication() {
+        let config = config();
+        let (storage, code) = storage_with_code_for(&config, request("openid profile", Some("nonce"))).await;
+
+        let (status, body) = request_token(&storage, &config, &code_form(&code.code)).await;
+        assert_eq!(status, 200);
+
+        let claims = id_token_claims(&body);
+        assert_eq!(claims.sub, "user");
+        assert_eq!(claims.auth_time, Some(AUTH_TIME));
+        assert_eq!(claims.acr.as_deref(), Some(ACR_MULTI_FACTOR));
+        assert_eq!(claims.nonce.as_deref(), Some("nonce"));
+
+        // The nonce belongs to the authorization request, it is not repeated on refresh
+        let (_, refreshed) = request_token(&storage, &config, &refresh_form(token_field(&body, "refresh_token"))).await;
+        let claims = id_token_claims(&refreshed);
+        assert_eq!(claims.auth_time, Some(AUTH_TIME));
+        assert_eq!(claims.acr.as_deref(), Some(ACR_MULTI_FACTOR));
+        assert_eq!(claims.nonce, None);
+    }
+}
@@ -0,0 +1,41 @@
+use actix_web::web;
+use serde::Serialize;
+use database::consent::Consent;
+use database::oauth2_client::OAuth2Client;
+use crate::routes::appdata::WDatabase;
+use crate::routes::auth::Auth;
+use crate::routes::error::WebResult;
+
+#[derive(Serialize)]
+pub struct App {
+    client_id: String,
+    client_name: String,
+    /// The scopes the user has consented to
+    scopes: Vec<String>,
+    granted_at: i64,
+}
+
+pub async fn list(database: WDatabase, auth: Auth) -> WebResult<web::Json<Vec<App>>> {
+    auth.require_first_party()?;
+
+    let mut apps = Vec::new();
+    for consent in Consent::list_by_user_id(&database, &auth.user.user_id).await? {
+        // The client may have been deleted since
+        let client = match OAuth2Client::get_by_client_id(&database, &consent.client_id).await? {
+            Some(client) => client,
+            None => continue,
+        };
+
+        let mut scopes = consent.scopes(&database).await?.into_iter().collect::<Vec<_>>();
+        scopes.sort();
+
+        apps.push(App {
+            client_id: client.client_id,
+            client_name: client.name,
+            scopes,
+            granted_at: consent.granted_at,
+        });
+    }
+
+    Ok(web::Json(apps))
+}
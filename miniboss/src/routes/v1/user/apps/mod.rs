@@ -0,0 +1,18 @@
+use actix_route_config::Routable;
+use actix_web::web;
+use actix_web::web::ServiceConfig;
+
+mod list;
+mod revoke;
+
+/// Clients the user has given access to their account
+pub struct Router;
+
+impl Routable for Router {
+    fn configure(config: &mut ServiceConfig) {
+        config.service(web::scope("/apps")
+            .route("", web::get().to(list::list))
+            .route("/{client_id}", web::delete().to(revoke::revoke))
+        );
+    }
+}
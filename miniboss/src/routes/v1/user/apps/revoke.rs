@@ -0,0 +1,23 @@
+use actix_web::web;
+use database::consent::Consent;
+use crate::routes::appdata::WDatabase;
+use crate::routes::auth::Auth;
+use crate::routes::empty::Empty;
+use crate::routes::error::{WebError, WebResult};
+
+/// Revoke the client's access. The user will be asked for consent again on the next authorization.
+pub async fn revoke(
+    database: WDatabase,
+    auth: Auth,
+    client_id: web::Path<String>,
+) -> WebResult<Empty> {
+    auth.require_first_party()?;
+
+    let consent = Consent::get(&database, &auth.user.user_id, &client_id)
+        .await?
+        .ok_or(WebError::NotFound)?;
+
+    consent.revoke(&database).await?;
+
+    Ok(Empty)
+}
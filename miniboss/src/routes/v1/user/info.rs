@@ -1,8 +1,37 @@
 use actix_web::web;
+use serde::Serialize;
 
+use database::group::Group;
 use database::user::User;
+use crate::routes::appdata::WDatabase;
 use crate::routes::auth::Auth;
+use crate::routes::error::WebResult;
+use crate::routes::scopes::GROUPS_SCOPE;
 
-pub async fn info(auth: Auth) -> web::Json<User> {
-    web::Json(auth.user)
-}
\ No newline at end of file
+#[derive(Serialize)]
+pub struct Response {
+    #[serde(flatten)]
+    user: User,
+    /// Names of the groups the user is a member of.
+    /// Only present if the `groups` scope was granted.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    groups: Option<Vec<String>>,
+}
+
+pub async fn info(database: WDatabase, auth: Auth) -> WebResult<web::Json<Response>> {
+    let groups = if auth.has_scope(GROUPS_SCOPE) {
+        let groups = Group::list_by_user_id(&database, &auth.user.user_id)
+            .await?
+            .into_iter()
+            .map(|group| group.name)
+            .collect();
+        Some(groups)
+    } else {
+        None
+    };
+
+    Ok(web::Json(Response {
+        user: auth.user,
+        groups,
+    }))
+}
@@ -4,6 +4,10 @@ use actix_web::web::ServiceConfig;
 
 mod register;
 mod info;
+mod totp;
+mod webauthn;
+mod tokens;
+mod apps;
 
 pub struct Router;
 
@@ -12,6 +16,10 @@ impl Routable for Router {
         config.service(web::scope("/user")
             .route("/register", web::post().to(register::register))
             .route("/info", web::get().to(info::info))
+            .configure(totp::Router::configure)
+            .configure(webauthn::Router::configure)
+            .configure(tokens::Router::configure)
+            .configure(apps::Router::configure)
         );
     }
 }
\ No newline at end of file
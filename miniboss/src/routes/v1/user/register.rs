@@ -1,7 +1,7 @@
 use actix_web::web;
 use serde::{Deserialize, Serialize};
 use database::user::User;
-use crate::routes::appdata::{WConfig, WDatabase};
+use crate::routes::appdata::{WConfig, WDatabase, WPasswordPolicy};
 use crate::routes::error::{WebError, WebResult};
 
 #[derive(Deserialize)]
@@ -19,9 +19,13 @@ pub struct Response {
 pub async fn register(
     database: WDatabase,
     config: WConfig,
+    password_policy: WPasswordPolicy,
     payload: web::Json<Request>
 ) -> WebResult<web::Json<Response>> {
     let payload = payload.into_inner();
+    // Check before creating the user, so it isn't left without a password
+    password_policy.check(&payload.password, &payload.email)?;
+
     if User::get_by_email(&database, &payload.email).await?.is_some() {
         return Err(WebError::BadRequest);
     }
@@ -35,7 +39,7 @@ pub async fn register(
         total_user_count == 0
     ).await?;
 
-    user.set_password(&payload.password, &config.password_pepper, &database).await?;
+    user.set_password(&payload.password, &password_policy, &config.password_hashing(), &database).await?;
 
     Ok(web::Json(Response {
         id: user.user_id,
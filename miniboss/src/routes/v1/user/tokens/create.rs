@@ -0,0 +1,68 @@
+use actix_web::cookie::time::OffsetDateTime;
+use actix_web::web;
+use serde::{Deserialize, Serialize};
+use database::personal_access_token::PersonalAccessToken;
+use crate::routes::appdata::WDatabase;
+use crate::routes::auth::Auth;
+use crate::routes::error::{WebError, WebResult};
+use crate::routes::scopes::{check_scopes_permitted, parse_scopes};
+use crate::routes::v1::user::tokens::Token;
+
+#[derive(Deserialize)]
+pub struct Request {
+    name: String,
+    /// Space separated, like an OAuth2 `scope`.
+    /// Administrators need `miniboss.admin` for the token to access the admin API.
+    scope: Option<String>,
+    /// Unix timestamp. The token never expires if not set.
+    expires_at: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct Response {
+    /// The token itself. This is only shown once.
+    token: String,
+    #[serde(flatten)]
+    info: Token,
+}
+
+pub async fn create(
+    database: WDatabase,
+    auth: Auth,
+    payload: web::Json<Request>,
+) -> WebResult<web::Json<Response>> {
+    auth.require_first_party()?;
+    let payload = payload.into_inner();
+
+    if payload.name.is_empty() || payload.name.len() > 64 {
+        return Err(WebError::BadRequest);
+    }
+
+    if let Some(expires_at) = payload.expires_at {
+        if expires_at <= OffsetDateTime::now_utc().unix_timestamp() {
+            return Err(WebError::BadRequest);
+        }
+    }
+
+    if PersonalAccessToken::get_by_name(&database, &auth.user.user_id, &payload.name).await?.is_some() {
+        return Err(WebError::BadRequest);
+    }
+
+    // A token can't be granted more than its owner is permitted
+    let scopes = parse_scopes(payload.scope.as_deref());
+    check_scopes_permitted(&database, &auth.user, &scopes).await?;
+    let scope = (!scopes.is_empty()).then(|| scopes.into_iter().collect::<Vec<_>>().join(" "));
+
+    let (info, token) = PersonalAccessToken::new(
+        &database,
+        &auth.user.user_id,
+        payload.name,
+        scope,
+        payload.expires_at,
+    ).await?;
+
+    Ok(web::Json(Response {
+        token,
+        info: info.into(),
+    }))
+}
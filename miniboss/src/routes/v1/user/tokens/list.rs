@@ -0,0 +1,18 @@
+use actix_web::web;
+use database::personal_access_token::PersonalAccessToken;
+use crate::routes::appdata::WDatabase;
+use crate::routes::auth::Auth;
+use crate::routes::error::WebResult;
+use crate::routes::v1::user::tokens::Token;
+
+pub async fn list(database: WDatabase, auth: Auth) -> WebResult<web::Json<Vec<Token>>> {
+    auth.require_first_party()?;
+
+    let tokens = PersonalAccessToken::list_by_user_id(&database, &auth.user.user_id)
+        .await?
+        .into_iter()
+        .map(Token::from)
+        .collect();
+
+    Ok(web::Json(tokens))
+}
@@ -0,0 +1,44 @@
+use actix_route_config::Routable;
+use actix_web::web;
+use actix_web::web::ServiceConfig;
+use serde::Serialize;
+use database::personal_access_token::PersonalAccessToken;
+
+mod create;
+mod list;
+mod revoke;
+
+pub struct Router;
+
+impl Routable for Router {
+    fn configure(config: &mut ServiceConfig) {
+        config.service(web::scope("/tokens")
+            .route("", web::get().to(list::list))
+            .route("", web::post().to(create::create))
+            .route("/{id}", web::delete().to(revoke::revoke))
+        );
+    }
+}
+
+#[derive(Serialize)]
+pub struct Token {
+    id: String,
+    name: String,
+    scope: String,
+    created_at: i64,
+    expires_at: Option<i64>,
+    last_used_at: Option<i64>,
+}
+
+impl From<PersonalAccessToken> for Token {
+    fn from(value: PersonalAccessToken) -> Self {
+        Self {
+            id: value.id,
+            name: value.name,
+            scope: value.scopes.unwrap_or_default(),
+            created_at: value.created_at,
+            expires_at: value.expires_at,
+            last_used_at: value.last_used_at,
+        }
+    }
+}
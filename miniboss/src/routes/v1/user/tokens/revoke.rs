@@ -0,0 +1,23 @@
+use actix_web::web;
+use database::personal_access_token::PersonalAccessToken;
+use crate::routes::appdata::WDatabase;
+use crate::routes::auth::Auth;
+use crate::routes::empty::Empty;
+use crate::routes::error::{WebError, WebResult};
+
+pub async fn revoke(
+    database: WDatabase,
+    auth: Auth,
+    id: web::Path<String>,
+) -> WebResult<Empty> {
+    auth.require_first_party()?;
+
+    let token = PersonalAccessToken::get_by_id(&database, &id)
+        .await?
+        .filter(|token| token.user_id.eq(&auth.user.user_id))
+        .ok_or(WebError::NotFound)?;
+
+    token.delete(&database).await?;
+
+    Ok(Empty)
+}
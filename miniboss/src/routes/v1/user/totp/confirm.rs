@@ -0,0 +1,43 @@
+use actix_web::web;
+use serde::{Deserialize, Serialize};
+use database::totp::UserTotp;
+use crate::routes::appdata::WDatabase;
+use crate::routes::auth::Auth;
+use crate::routes::error::{WebError, WebResult};
+
+#[derive(Deserialize)]
+pub struct Request {
+    code: String,
+}
+
+#[derive(Serialize)]
+pub struct Response {
+    /// One-time recovery codes. These are only shown once.
+    recovery_codes: Vec<String>,
+}
+
+pub async fn confirm(
+    database: WDatabase,
+    auth: Auth,
+    payload: web::Json<Request>,
+) -> WebResult<web::Json<Response>> {
+    auth.require_first_party()?;
+
+    let mut totp = UserTotp::get_by_user_id(&database, &auth.user.user_id)
+        .await?
+        .ok_or(WebError::NotFound)?;
+
+    if totp.confirmed {
+        return Err(WebError::BadRequest);
+    }
+
+    if !totp.verify(&database, &payload.code).await? {
+        return Err(WebError::Unauthorized);
+    }
+
+    let recovery_codes = totp.confirm(&database).await?;
+
+    Ok(web::Json(Response {
+        recovery_codes,
+    }))
+}
@@ -0,0 +1,36 @@
+use actix_web::web;
+use serde::Deserialize;
+use database::totp::{RecoveryCode, UserTotp};
+use crate::routes::appdata::WDatabase;
+use crate::routes::auth::Auth;
+use crate::routes::empty::Empty;
+use crate::routes::error::{WebError, WebResult};
+
+#[derive(Deserialize)]
+pub struct Request {
+    /// Either a code from the user's authenticator, or one of their recovery codes
+    code: String,
+}
+
+pub async fn disable(
+    database: WDatabase,
+    auth: Auth,
+    payload: web::Json<Request>,
+) -> WebResult<Empty> {
+    auth.require_first_party()?;
+
+    let mut totp = UserTotp::get_confirmed(&database, &auth.user.user_id)
+        .await?
+        .ok_or(WebError::NotFound)?;
+
+    let valid = totp.verify(&database, &payload.code).await?
+        || RecoveryCode::consume(&database, &auth.user.user_id, &payload.code).await?;
+
+    if !valid {
+        return Err(WebError::Unauthorized);
+    }
+
+    totp.delete(&database).await?;
+
+    Ok(Empty)
+}
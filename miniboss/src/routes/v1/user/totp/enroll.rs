@@ -0,0 +1,33 @@
+use actix_web::web;
+use serde::Serialize;
+use database::totp::UserTotp;
+use crate::routes::appdata::{WConfig, WDatabase};
+use crate::routes::auth::Auth;
+use crate::routes::error::{WebError, WebResult};
+
+#[derive(Serialize)]
+pub struct Response {
+    /// Base32 encoded secret, for manual entry
+    secret: String,
+    /// `otpauth://` URI, to be displayed as a QR code
+    provisioning_uri: String,
+}
+
+pub async fn enroll(
+    database: WDatabase,
+    config: WConfig,
+    auth: Auth,
+) -> WebResult<web::Json<Response>> {
+    auth.require_first_party()?;
+
+    if UserTotp::get_confirmed(&database, &auth.user.user_id).await?.is_some() {
+        return Err(WebError::BadRequest);
+    }
+
+    let totp = UserTotp::enroll(&database, &auth.user.user_id).await?;
+
+    Ok(web::Json(Response {
+        secret: totp.secret().to_string(),
+        provisioning_uri: totp.provisioning_uri(&config.totp.issuer, &auth.user.email)?,
+    }))
+}
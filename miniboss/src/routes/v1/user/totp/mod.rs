@@ -0,0 +1,21 @@
+use actix_route_config::Routable;
+use actix_web::web;
+use actix_web::web::ServiceConfig;
+
+mod enroll;
+mod confirm;
+mod disable;
+mod recovery_codes;
+
+pub struct Router;
+
+impl Routable for Router {
+    fn configure(config: &mut ServiceConfig) {
+        config.service(web::scope("/totp")
+            .route("/enroll", web::post().to(enroll::enroll))
+            .route("/confirm", web::post().to(confirm::confirm))
+            .route("/disable", web::post().to(disable::disable))
+            .route("/recovery-codes", web::post().to(recovery_codes::recovery_codes))
+        );
+    }
+}
@@ -0,0 +1,41 @@
+use actix_web::web;
+use serde::{Deserialize, Serialize};
+use database::totp::{RecoveryCode, UserTotp};
+use crate::routes::appdata::WDatabase;
+use crate::routes::auth::Auth;
+use crate::routes::error::{WebError, WebResult};
+
+#[derive(Deserialize)]
+pub struct Request {
+    /// A code from the user's authenticator
+    code: String,
+}
+
+#[derive(Serialize)]
+pub struct Response {
+    /// One-time recovery codes. These are only shown once.
+    recovery_codes: Vec<String>,
+}
+
+/// Replace the user's recovery codes with a new set
+pub async fn recovery_codes(
+    database: WDatabase,
+    auth: Auth,
+    payload: web::Json<Request>,
+) -> WebResult<web::Json<Response>> {
+    auth.require_first_party()?;
+
+    let mut totp = UserTotp::get_confirmed(&database, &auth.user.user_id)
+        .await?
+        .ok_or(WebError::NotFound)?;
+
+    if !totp.verify(&database, &payload.code).await? {
+        return Err(WebError::Unauthorized);
+    }
+
+    let recovery_codes = RecoveryCode::regenerate(&database, &auth.user.user_id).await?;
+
+    Ok(web::Json(Response {
+        recovery_codes,
+    }))
+}
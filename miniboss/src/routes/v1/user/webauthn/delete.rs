@@ -0,0 +1,23 @@
+use actix_web::web;
+use database::webauthn::WebauthnCredential;
+use crate::routes::appdata::WDatabase;
+use crate::routes::auth::Auth;
+use crate::routes::empty::Empty;
+use crate::routes::error::{WebError, WebResult};
+
+pub async fn delete(
+    database: WDatabase,
+    auth: Auth,
+    id: web::Path<String>,
+) -> WebResult<Empty> {
+    auth.require_first_party()?;
+
+    let credential = WebauthnCredential::get_by_credential_id(&database, &id)
+        .await?
+        .filter(|credential| credential.user_id.eq(&auth.user.user_id))
+        .ok_or(WebError::NotFound)?;
+
+    credential.delete(&database).await?;
+
+    Ok(Empty)
+}
@@ -0,0 +1,16 @@
+use actix_web::web;
+use database::webauthn::WebauthnCredential;
+use crate::routes::appdata::WDatabase;
+use crate::routes::auth::Auth;
+use crate::routes::error::WebResult;
+use crate::routes::v1::user::webauthn::Credential;
+
+pub async fn list(database: WDatabase, auth: Auth) -> WebResult<web::Json<Vec<Credential>>> {
+    let credentials = WebauthnCredential::list_by_user_id(&database, &auth.user.user_id)
+        .await?
+        .into_iter()
+        .map(Credential::from)
+        .collect();
+
+    Ok(web::Json(credentials))
+}
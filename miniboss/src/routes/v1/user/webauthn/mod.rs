@@ -0,0 +1,44 @@
+use actix_route_config::Routable;
+use actix_web::web;
+use actix_web::web::ServiceConfig;
+use serde::Serialize;
+use database::webauthn::WebauthnCredential;
+
+mod register_start;
+mod register_finish;
+mod list;
+mod rename;
+mod delete;
+
+pub struct Router;
+
+impl Routable for Router {
+    fn configure(config: &mut ServiceConfig) {
+        config.service(web::scope("/webauthn")
+            .route("/register/start", web::post().to(register_start::register_start))
+            .route("/register/finish", web::post().to(register_finish::register_finish))
+            .route("/credentials", web::get().to(list::list))
+            .route("/credentials/{id}", web::patch().to(rename::rename))
+            .route("/credentials/{id}", web::delete().to(delete::delete))
+        );
+    }
+}
+
+#[derive(Serialize)]
+pub struct Credential {
+    id: String,
+    name: String,
+    created_at: i64,
+    last_used_at: Option<i64>,
+}
+
+impl From<WebauthnCredential> for Credential {
+    fn from(value: WebauthnCredential) -> Self {
+        Self {
+            id: value.credential_id,
+            name: value.name,
+            created_at: value.created_at,
+            last_used_at: value.last_used_at,
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use actix_web::web;
+use serde::Deserialize;
+use webauthn_rs::prelude::{PasskeyRegistration, RegisterPublicKeyCredential};
+use database::webauthn::{WebauthnChallenge, WebauthnCredential};
+use crate::routes::appdata::{WConfig, WDatabase};
+use crate::routes::auth::Auth;
+use crate::routes::error::{WebError, WebResult};
+use crate::routes::v1::user::webauthn::Credential;
+
+#[derive(Deserialize)]
+pub struct Request {
+    challenge_id: String,
+    /// A name for the user to recognize the credential by
+    name: String,
+    /// The result of `navigator.credentials.create()`
+    credential: RegisterPublicKeyCredential,
+}
+
+pub async fn register_finish(
+    database: WDatabase,
+    config: WConfig,
+    auth: Auth,
+    payload: web::Json<Request>,
+) -> WebResult<web::Json<Credential>> {
+    auth.require_first_party()?;
+
+    let webauthn = config.webauthn.as_ref().ok_or(WebError::NotFound)?.build()?;
+    let payload = payload.into_inner();
+
+    if payload.name.is_empty() || payload.name.len() > 64 {
+        return Err(WebError::BadRequest);
+    }
+
+    // The authenticator must have created a discoverable credential, as requested by `register_start`.
+    // Browsers report this through the `credProps` extension.
+    let discoverable = payload.credential.extensions.cred_props.as_ref().is_some_and(|props| props.rk);
+    if !discoverable {
+        return Err(WebError::BadRequest);
+    }
+
+    let state: PasskeyRegistration = WebauthnChallenge::take(&database, &payload.challenge_id, &auth.user.user_id)
+        .await?
+        .ok_or(WebError::NotFound)?;
+
+    let passkey = webauthn.finish_passkey_registration(&payload.credential, &state)?;
+    let credential = WebauthnCredential::new(&database, &auth.user.user_id, payload.name, &passkey).await?;
+
+    Ok(web::Json(credential.into()))
+}
@@ -0,0 +1,52 @@
+use actix_web::web;
+use serde::Serialize;
+use webauthn_rs::prelude::CreationChallengeResponse;
+use webauthn_rs_proto::ResidentKeyRequirement;
+use database::webauthn::{user_handle, WebauthnChallenge, WebauthnCredential};
+use crate::routes::appdata::{WConfig, WDatabase};
+use crate::routes::auth::Auth;
+use crate::routes::error::{WebError, WebResult};
+
+#[derive(Serialize)]
+pub struct Response {
+    challenge_id: String,
+    /// To be passed to `navigator.credentials.create()`
+    options: CreationChallengeResponse,
+}
+
+pub async fn register_start(
+    database: WDatabase,
+    config: WConfig,
+    auth: Auth,
+) -> WebResult<web::Json<Response>> {
+    auth.require_first_party()?;
+
+    let webauthn = config.webauthn.as_ref().ok_or(WebError::NotFound)?.build()?;
+
+    // Don't allow registering the same authenticator twice
+    let existing = WebauthnCredential::list_by_user_id(&database, &auth.user.user_id)
+        .await?
+        .iter()
+        .map(|credential| credential.passkey().map(|passkey| passkey.cred_id().clone()))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let (mut options, state) = webauthn.start_passkey_registration(
+        user_handle(&auth.user.user_id),
+        &auth.user.email,
+        &auth.user.name,
+        Some(existing),
+    )?;
+
+    // Passkeys are offered for usernameless login, so they must be discoverable
+    if let Some(selection) = options.public_key.authenticator_selection.as_mut() {
+        selection.resident_key = Some(ResidentKeyRequirement::Required);
+        selection.require_resident_key = true;
+    }
+
+    let challenge_id = WebauthnChallenge::store(&database, &auth.user.user_id, &state).await?;
+
+    Ok(web::Json(Response {
+        challenge_id,
+        options,
+    }))
+}
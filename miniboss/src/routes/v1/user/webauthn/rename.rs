@@ -0,0 +1,35 @@
+use actix_web::web;
+use serde::Deserialize;
+use database::webauthn::WebauthnCredential;
+use crate::routes::appdata::WDatabase;
+use crate::routes::auth::Auth;
+use crate::routes::error::{WebError, WebResult};
+use crate::routes::v1::user::webauthn::Credential;
+
+#[derive(Deserialize)]
+pub struct Request {
+    name: String,
+}
+
+pub async fn rename(
+    database: WDatabase,
+    auth: Auth,
+    id: web::Path<String>,
+    payload: web::Json<Request>,
+) -> WebResult<web::Json<Credential>> {
+    auth.require_first_party()?;
+
+    let payload = payload.into_inner();
+    if payload.name.is_empty() || payload.name.len() > 64 {
+        return Err(WebError::BadRequest);
+    }
+
+    let mut credential = WebauthnCredential::get_by_credential_id(&database, &id)
+        .await?
+        .filter(|credential| credential.user_id.eq(&auth.user.user_id))
+        .ok_or(WebError::NotFound)?;
+
+    credential.rename(&database, payload.name).await?;
+
+    Ok(web::Json(credential.into()))
+}